@@ -0,0 +1,53 @@
+//! Compares the spatial hash broadphase in `gather_contacts` against testing every pair.
+//! Run with `cargo bench --bench broadphase`.
+use engine2d::collision::*;
+use engine2d::types::Vec2i;
+use std::time::{Duration, Instant};
+
+/// A scattering of n entities, mostly 16x16 with a few bigger ones, at about the density of a busy level
+fn scene(n: usize) -> (Vec<Vec2i>, Vec<(usize, usize)>) {
+    // A small LCG so runs are repeatable without pulling in a rand crate
+    let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut next = move |bound: u64| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) % bound
+    };
+    let side = ((n * 32 * 32) as f64).sqrt() as u64;
+    let positions = (0..n)
+        .map(|_| Vec2i(next(side) as i32, next(side) as i32))
+        .collect();
+    let sizes = (0..n)
+        .map(|i| if i % 50 == 0 { (48, 48) } else { (16, 16) })
+        .collect();
+    (positions, sizes)
+}
+
+/// Average time per call over enough calls to take about half a second
+fn time(f: impl Fn() -> usize) -> (Duration, usize) {
+    let start = Instant::now();
+    let mut runs = 0;
+    let mut found = 0;
+    while runs < 3 || start.elapsed() < Duration::from_millis(500) {
+        found = f();
+        runs += 1;
+    }
+    (start.elapsed() / runs, found)
+}
+
+fn main() {
+    println!("{:>8} {:>14} {:>14} {:>10}", "entities", "brute force", "spatial hash", "contacts");
+    for &n in &[10, 100, 1000, 5000] {
+        let (positions, sizes) = scene(n);
+        let colliders = vec![Collider::default(); n];
+        let (brute, brute_found) = time(|| gather_contacts_brute(&positions, &sizes, &colliders).len());
+        let (grid, grid_found) = time(|| gather_contacts(&positions, &sizes, &colliders).len());
+        assert_eq!(brute_found, grid_found, "Broadphase missed or duplicated contacts");
+        assert!(
+            gather_contacts(&positions, &sizes, &colliders) == gather_contacts_brute(&positions, &sizes, &colliders),
+            "Broadphase contacts differ from brute force"
+        );
+        println!("{:>8} {:>14?} {:>14?} {:>10}", n, brute, grid, grid_found);
+    }
+}
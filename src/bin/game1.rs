@@ -10,14 +10,23 @@ use winit_input_helper::WinitInputHelper;
 use engine2d::{text::DrawText, types::*};
 use engine2d::graphics::Screen;
 use engine2d::tiles::*;
+use engine2d::level::{Spawn, LevelFile};
+use engine2d::pathfinding::*;
+use engine2d::raycast::VisionCone;
 use engine2d::animation::*;
 use engine2d::collision::*;
+use engine2d::shapes::Shape;
+use engine2d::physics::{Body, sync_bodies};
+use engine2d::controller::{TopDownController, arrow_axis};
+use engine2d::sprite::Direction;
+use engine2d::query::{World, Hit};
 
 use std::fs::File;
 use std::io::BufReader;
 use std::time::Duration;
 use rodio::{Decoder, OutputStream, Sink};
 use rodio::source::{SineWave, Source};
+use serde::{Serialize, Deserialize};
 
 // use engine2d::collision::*;
 // Imagine a Resources struct (we'll call it AssetDB or Assets in the future)
@@ -28,7 +37,7 @@ use engine2d::texture::Texture;
 const WIDTH: usize = 16*20;
 const HEIGHT: usize = 16*20;
 
-#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+#[derive(Clone,Copy,PartialEq,Eq,Debug,Serialize,Deserialize)]
 enum EntityType {
     Player,
     Enemy,
@@ -37,7 +46,14 @@ enum EntityType {
     Destination,
 }
 
-type Level = (Tilemap, Vec<(EntityType, i32, i32)>);
+type Level = (Tilemap, Vec<Spawn<EntityType>>);
+
+// Collision layers
+const PLAYER: u32 = 1;
+const WALLS: u32 = 2;
+const ENEMIES: u32 = 4;
+const GOALS: u32 = 8;
+const TILES: u32 = 16;
 type Input = WinitInputHelper;
 
 #[derive(Debug,Clone,Copy)]
@@ -56,7 +72,7 @@ enum PlayMode {
 // Crates for music: kira, rodio. There will be a set up at the beginning of your program.
 impl Mode {
     // update consumes self and yields a new state (which might also just be self)
-    fn update(self, game:&mut GameState, input:&Input) -> Self {
+    fn update(self, game:&mut GameState, input:&Input, levels:&Vec<Level>) -> Self {
         match self {
             Mode::Title => {
                 // if input.mouse()!=None {
@@ -81,6 +97,7 @@ impl Mode {
                         Vec2i(4*16,3*16),
                         Vec2i(9*16,27*16),
                     ];
+                    game.contact_tracker.clear();
                     game.mode = Mode::EndGame;
                     Mode::EndGame
                 } else {
@@ -98,50 +115,27 @@ impl Mode {
                     //     Mode::EndGame
                     // }  
                     // Player control goes here
-                    if input.key_held(VirtualKeyCode::Right) {
-                        if game.positions[0].0 < 16*19 {
-                            game.velocities[0].0 = 2;
-                        } else {
-                            game.velocities[0].0 = 0;
-                        }
-                        game.anim_state[0].change_time(2);
-                    } else if input.key_held(VirtualKeyCode::Left) {
-                        if game.positions[0].0 > 0 {
-                            game.velocities[0].0 = -2;
-                        } else {
-                            game.velocities[0].0 = 0;
-                        }
-                        game.anim_state[0].change_time(1);
-                    } else {
-                        game.velocities[0].0 = 0;
+                    let axis = arrow_axis(input);
+                    sync_bodies(std::slice::from_mut(&mut game.player_body), &game.positions[..1]);
+                    game.player_control.update(&mut game.player_body, game.sizes[0], axis, std::slice::from_ref(&levels[game.level].0));
+                    game.positions[0] = game.player_body.pixel();
+                    if axis != Vec2f(0.0, 0.0) {
+                        game.anim_state[0].change_time(match game.player_control.facing {
+                            Direction::Down => 0,
+                            Direction::Left => 1,
+                            Direction::Right => 2,
+                            Direction::Up => 3,
+                        });
                     }
-                    if input.key_held(VirtualKeyCode::Up) {
-                        game.anim_state[0].change_time(3);
-                        if game.positions[0].1 > 0 {
-                            game.velocities[0].1 = -2;
-                        } else {
-                            game.velocities[0].1 = 0;
-                        }
-                        if game.positions[0].1 <= game.camera.1+16*5 && 
-                            game.camera.1 > 0 {
-                            game.camera.1 -= 2;
-                        }
-                    } else if input.key_held(VirtualKeyCode::Down) {
-                        if game.positions[0].1 < 28*16 {
-                            game.velocities[0].1 = 2;
-                        } else {
-                            game.velocities[0].1 = 0;
-                        }
-                        game.anim_state[0].change_time(0);
-                        if game.positions[0].1 >= game.camera.1+16*5 && 
-                            game.camera.1 < 10*16 {
-                            game.camera.1 += 2;
-                        }
-                    } else {
-                        game.velocities[0].1 = 0;
+                    if axis.1 < 0.0 && game.positions[0].1 <= game.camera.1+16*5 && 
+                        game.camera.1 > 0 {
+                        game.camera.1 -= 2;
+                    } else if axis.1 > 0.0 && game.positions[0].1 >= game.camera.1+16*5 && 
+                        game.camera.1 < 10*16 {
+                        game.camera.1 += 2;
                     }
                 } else { // on not movable
-                    game.velocities[0] = Vec2i(0,0);
+                    game.player_body.velocity = Vec2f(0.0, 0.0);
                 }
 
                 // Determine blocker velocity
@@ -149,24 +143,36 @@ impl Mode {
                     game.velocities[3].0 *= -1;
                 }
 
-                // Determine enemy velocity - chasing the character
-                if square(game.positions[1].0-game.positions[0].0)+square(game.positions[1].1-game.positions[0].1) <= 8000 {
-                    if game.positions[1].1 > game.positions[0].1 {
-                        game.velocities[1].1 = -1;
+                // Determine enemy velocity - chasing the character around the fire once it's been spotted
+                game.velocities[1] = Vec2i(0,0);
+                let map = &levels[game.level].0;
+                let center = |i: usize| Vec2i(game.positions[i].0 + game.sizes[i].0 as i32 / 2,
+                                              game.positions[i].1 + game.sizes[i].1 as i32 / 2);
+                let (enemy, player) = (center(1), center(0));
+                let vision = VisionCone::new(game.enemy_facing, std::f32::consts::FRAC_PI_3, 96.0);
+                if vision.sees(map, enemy, player) {
+                    let opts = PathOptions::for_entity(map, Connectivity::Four, game.sizes[1]);
+                    let path = find_path_with(map, map.tile_coord(enemy), map.tile_coord(player), &opts,
+                                              |tile| if tile.map_or(false, |t| t.hazard) { None } else { Some(1) });
+                    if let Some(path) = path {
+                        // Line up with the tile we're in before heading for the next one
+                        let steps = world_path(map, &path);
+                        let target = if steps[0] != game.positions[1] || steps.len() == 1 { steps[0] } else { steps[1] };
+                        game.velocities[1] = Vec2i((target.0 - game.positions[1].0).signum(),
+                                                   (target.1 - game.positions[1].1).signum());
+                    }
+                    // Keep looking at the player while chasing
+                    game.enemy_facing = Vec2f((player.0 - enemy.0) as f32, (player.1 - enemy.1) as f32);
+                    if game.velocities[1].1 < 0 {
                         game.anim_state[1].change_time(0);
-                    } else if game.positions[1].1 < game.positions[0].1 {
-                        game.velocities[1].1 = 1;
+                    } else if game.velocities[1].1 > 0 {
                         game.anim_state[1].change_time(3);
                     }
-                    if game.positions[1].0 > game.positions[0].0 {
-                        game.velocities[1].0 = -1;
+                    if game.velocities[1].0 < 0 {
                         game.anim_state[1].change_time(1);
-                    } else if game.positions[1].0 < game.positions[0].0 {
-                        game.velocities[1].0 = 1;
+                    } else if game.velocities[1].0 > 0 {
                         game.anim_state[1].change_time(2);
                     }
-                } else {
-                    game.velocities[1] = Vec2i(0,0);
                 }
 
                 // Update all positions
@@ -176,7 +182,13 @@ impl Mode {
                 }
 
                 // Die and return to start if touches fire
-                if game.positions[0].0 <= 1*16 || game.positions[0].0 >= 17*16+8 {
+                let center = Vec2i(game.positions[0].0 + game.sizes[0].0 as i32 / 2,
+                                   game.positions[0].1 + game.sizes[0].1 as i32 / 2);
+                let world = World::new(&game.positions, &game.sizes, &game.colliders, std::slice::from_ref(&levels[game.level].0), TILES);
+                let on_fire = world.overlap_point(center, TILES)
+                    .iter()
+                    .any(|h| matches!(h, Hit::Tile { tile, .. } if tile.hazard));
+                if on_fire {
                     game.movable = false;
                     // Play the explosion sound 
                     let (_, temp_stream_handle) = OutputStream::try_default().unwrap();
@@ -190,6 +202,7 @@ impl Mode {
                     game.positions[0].1 = 0;
                     game.positions[1] = Vec2i(10*16, 25*16); 
                     game.camera = Vec2i(0, 0);
+                    game.contact_tracker.clear();
                     game.movable = true;
                 }
 
@@ -202,6 +215,7 @@ impl Mode {
                         Vec2i(4*16,3*16),
                         Vec2i(9*16,27*16),
                     ];
+                    game.contact_tracker.clear();
                     game.mode = Mode::EndGame;
                     Mode::EndGame
                 } else {
@@ -221,6 +235,7 @@ impl Mode {
                         Vec2i(4*16,3*16),
                         Vec2i(9*16,27*16),
                     ];
+                    game.contact_tracker.clear();
                     game.level=0;
                     game.mode = Mode::Title;
                     Mode::Title
@@ -230,8 +245,8 @@ impl Mode {
             }
         }
     }
-    fn display(&self, game:&GameState, screen: &mut Screen, levels: &Vec<Level>,resources:&Resources) {
-        levels[game.level].0.draw(screen);
+    fn display(&self, game:&GameState, screen: &mut Screen, levels: &Vec<Level>,resources:&Resources, frame:usize) {
+        levels[game.level].0.draw(screen, frame);
         match self {
             Mode::Title => {
                 screen.draw_text(
@@ -265,8 +280,17 @@ struct GameState {
     positions: Vec<Vec2i>,
     velocities: Vec<Vec2i>,
     sizes:Vec<(usize,usize)>,
+    masses:Vec<Mass>,
+    colliders:Vec<Collider>,
+    // Last tick's contacts, so we only react when something first touches
+    contact_tracker:ContactTracker<usize,usize>,
     textures:Vec<Rc<Texture>>,
     anim_state:Vec<AnimationState>,
+    // The player moves through its body, so it keeps sub-pixel positions
+    player_body:Body,
+    player_control:TopDownController,
+    // Which way the enemy is looking
+    enemy_facing:Vec2f,
     // Camera position
     camera:Vec2i,
     mode:Mode, 
@@ -286,114 +310,89 @@ fn main() {
 
     // Here's our resources...
     let mut rsrc = Resources::new();
-    let tileset = Rc::new(Tileset::new(
-        vec![
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},           
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},           
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-        ],
-        &rsrc.load_texture(Path::new("content/jack/collage.png"))
-    ));
     let tileset1 = Rc::new(Tileset::new(
         vec![
-            Tile{solid:false},
-            Tile{solid:false},
-            Tile{solid:false},
-            Tile{solid:false},
+            Tile::OPEN,
+            Tile::OPEN,
+            Tile::OPEN,
+            Tile::OPEN,
         ],
         &rsrc.load_texture(Path::new("content/jack/collage.png"))
     ));
 
     let tileset_title = Rc::new(Tileset::new(
         vec![ // Exactly 104 tiles. Which is 208 x 128 / (16 x 16)
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},           
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},           
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,           
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,           
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
         ],
         &rsrc.load_texture(Path::new("content/jack/ahaha.png"))
     ));
@@ -432,69 +431,22 @@ fn main() {
         ),
         // Initial entities on level start
             vec![
-                (EntityType::Player, 10, 0),
-                (EntityType::Enemy, 10, 25),
-                (EntityType::Blocker, 5, 13), 
-                (EntityType::HBlocker, 4, 3),
-                (EntityType::Destination, 9, 27),
+                Spawn::new(EntityType::Player, Vec2i(10*16, 0)),
+                Spawn::new(EntityType::Enemy, Vec2i(10*16, 25*16)),
+                Spawn::new(EntityType::Blocker, Vec2i(5*16, 13*16)), 
+                Spawn::new(EntityType::HBlocker, Vec2i(4*16, 3*16)),
+                Spawn::new(EntityType::Destination, Vec2i(9*16, 27*16)),
             ]
         ),
 
 
-        // First level - The map
-        (Tilemap::new(
-            Vec2i(0,0),
-            // Map size
-            (20, 30),
-            &tileset,
-            // Tile grid
-            vec![
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  20, 21, 21, 21, 21, 21, 21, 21, 21, 21, 22, 1,  1,  1,  1,  1,  3,  3,
-                3,  3,  30, 31, 31, 31, 31, 31, 31, 31, 31, 31, 32, 1,  1,  1,  1,  1,  3,  3,
-                3,  3,  30, 31, 31, 31, 31, 31, 31, 31, 31, 31, 32, 1,  1,  1,  1,  1,  3,  3,
-
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3, 
-
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3, 
-
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3, 
-
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3, 
-
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3,
-                3,  3,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  1,  3,  3, 
-                ],     
-            ),
-            // Plug in entities
-            vec![
-                (EntityType::Player, 10, 0),
-                (EntityType::Enemy, 10, 25),
-                (EntityType::Blocker, 5, 13), 
-                (EntityType::HBlocker, 4, 3),
-                (EntityType::Destination, 9, 27),
-            ]
-        ),
+        // First level - The map, which lives in a level file
+        {
+            let level = LevelFile::<EntityType>::load("content/game1/level1.json")
+                .and_then(|file| file.build("content/game1"))
+                .unwrap_or_else(|e| panic!("{}", e));
+            (level.tilemaps.into_iter().next().expect("Level 1 has no tilemap"), level.spawns)
+        },
 
     (Tilemap::new(
         Vec2i(0,0),
@@ -527,11 +479,11 @@ fn main() {
     ),
     // Initial entities on level start
     vec![
-        (EntityType::Player, 10, 0),
-        (EntityType::Enemy, 10, 25),
-        (EntityType::Blocker, 5, 13), 
-        (EntityType::HBlocker, 4, 3),
-        (EntityType::Destination, 9, 27),
+        Spawn::new(EntityType::Player, Vec2i(10*16, 0)),
+        Spawn::new(EntityType::Enemy, Vec2i(10*16, 25*16)),
+        Spawn::new(EntityType::Blocker, Vec2i(5*16, 13*16)), 
+        Spawn::new(EntityType::HBlocker, Vec2i(4*16, 3*16)),
+        Spawn::new(EntityType::Destination, Vec2i(9*16, 27*16)),
     ]
 )
     ];
@@ -569,36 +521,33 @@ fn main() {
         // Assume entity 0 is the player
         types: vec![
             // In a real example we'd provide nicer accessors than this
-            levels[1].1[0].0,
-            levels[1].1[1].0,
-            levels[1].1[2].0,
-            levels[1].1[3].0,
-            levels[1].1[4].0,
+            levels[1].1[0].kind,
+            levels[1].1[1].kind,
+            levels[1].1[2].kind,
+            levels[1].1[3].kind,
+            levels[1].1[4].kind,
         ],
         positions: vec![
-            Vec2i(
-                levels[1].1[0].1 * 16,
-                levels[1].1[0].2 * 16,
-            ),
-            Vec2i(
-                levels[1].1[1].1 * 16,
-                levels[1].1[1].2 * 16,
-            ), 
-            Vec2i(
-                levels[1].1[2].1 * 16,
-                levels[1].1[2].2 * 16,
-            ),
-            Vec2i(
-                levels[1].1[3].1 * 16,
-                levels[1].1[3].2 * 16,
-            ),
-            Vec2i(
-                levels[1].1[4].1 * 16,
-                levels[1].1[4].2 * 16,
-            ),
+            levels[1].1[0].position,
+            levels[1].1[1].position, 
+            levels[1].1[2].position,
+            levels[1].1[3].position,
+            levels[1].1[4].position,
         ],
         velocities: vec![Vec2i(0,0), Vec2i(0,0), Vec2i(0,0), Vec2i(1,0), Vec2i(0,0)],
         sizes: vec![(16,16), (16,16), (16,16), (32,32), (48,48)],
+        // Only the player gets pushed around; the blockers push it
+        masses: vec![Mass::Dynamic(1.0), Mass::Kinematic, Mass::Kinematic, Mass::Kinematic, Mass::Kinematic],
+        // The enemy and the destination only need to know when the player touches them
+        colliders: vec![
+            Collider::new(PLAYER, WALLS | ENEMIES | GOALS),
+            // The enemy is round, so just brushing a corner of its box doesn't count
+            Collider { shape: Shape::Circle, ..Collider::trigger(ENEMIES, PLAYER) },
+            Collider::new(WALLS, PLAYER),
+            Collider::new(WALLS, PLAYER),
+            Collider::trigger(GOALS, PLAYER),
+        ],
+        contact_tracker: ContactTracker::new(),
         // Could be texture handles instead, let's talk about that in two weeks
         textures: vec![Rc::clone(&player_tex),
                        Rc::clone(&enemy_tex), 
@@ -607,6 +556,11 @@ fn main() {
                        Rc::clone(&dest_tex),
                        ],
         anim_state: vec![player_anim.start(), enemy_anim.start(), blocker_anim.start(), red_anim.start(), dest_anim.start()],
+        player_body: Body { gravity_scale: 0.0, ..Body::new(levels[1].1[0].position) },
+        // Two pixels a tick, kept inside the level
+        player_control: TopDownController { bounds: Some(Rect::new(0, 0, 16*20, 16*29)), ..TopDownController::new(120.0) },
+        // The enemy starts out looking up towards where the player comes in
+        enemy_facing: Vec2f(0.0, -1.0),
         // Camera position
         camera: Vec2i(0, 0),
         mode:Mode::Title, 
@@ -641,21 +595,26 @@ fn main() {
 fn draw_game(resources:&Resources, levels: &Vec<Level>, state: &GameState, screen: &mut Screen, frame:usize) {
     screen.clear(Rgba(80, 80, 80, 255));
     screen.set_scroll(state.camera);
-    // levels[state.level].0.draw(screen);
+    // levels[state.level].0.draw(screen, frame);
     // for ((pos,tex),anim) in state.positions.iter().zip(state.textures.iter()).zip(state.anim_state.iter()) {
     //     screen.bitblt(tex,anim.frame(),*pos);
     // }
-    state.mode.display(&state, screen, levels,resources);
+    state.mode.display(&state, screen, levels,resources,frame);
 }
 
 fn update_game(resources:&Resources, levels: &Vec<Level>, state: &mut GameState, input: &WinitInputHelper, frame: usize) {
-    state.mode = state.mode.update(state, input);
+    state.mode = state.mode.update(state, input, levels);
     // Detect collisions: Convert positions and sizes to collision bodies, generate contacts
-    let contacts = engine2d::collision::gather_contacts(&state.positions, &state.sizes);
+    let contacts = engine2d::collision::gather_contacts(&state.positions, &state.sizes, &state.colliders);
     // Handle collisions: Apply restitution impulses.
+    engine2d::collision::restitute_dynamic(&mut state.positions, &state.sizes, &state.masses, &state.colliders, &contacts);
     // Update game rules: What happens when the player touches things? When enemies touch walls? Etc.
-    for contact in contacts.iter() {
-        match (levels[state.level].1[contact.a].0, levels[state.level].1[contact.b].0) {
+    for event in state.contact_tracker.update(&contacts) {
+        if event.phase != ContactPhase::Began {
+            continue;
+        }
+        let contact = event.contact;
+        match (levels[state.level].1[contact.a].kind, levels[state.level].1[contact.b].kind) {
             (EntityType::Player, EntityType::Enemy) => {
                 state.movable = false;
                 // let (_, temp_stream_handle) = OutputStream::try_default().unwrap();
@@ -668,35 +627,14 @@ fn update_game(resources:&Resources, levels: &Vec<Level>, state: &mut GameState,
                 state.positions[0].1 = 0;
                 state.positions[1] = Vec2i(10*16, 25*16); 
                 state.camera = Vec2i(0, 0);
+                state.contact_tracker.clear();
                 state.movable = true;
             }
-            (EntityType::Player, EntityType::Blocker) => {
-                if state.velocities[0].0 != 0{
-                    if state.positions[0].0 > state.positions[2].0 {
-                        state.positions[0].0 += contact.mtv.0;
-                    } else {
-                        state.positions[0].0 -= contact.mtv.0;
-                    }
-                }
-                if state.velocities[0].1 != 0 {
-                    if state.positions[0].1 > state.positions[2].1 {
-                        state.positions[0].1 += contact.mtv.1;
-                    } else {
-                        state.positions[0].1 -= contact.mtv.1;
-                    }
-                }
-            }
-            (EntityType::Player, EntityType::HBlocker) => {
-                if state.positions[0].0 > state.positions[3].0 {
-                    state.positions[0].0 += contact.mtv.0;
-                } else {
-                    state.positions[0].0 -= contact.mtv.0;
-                }
-            }
             (EntityType::Player, EntityType::Destination) => {
                 state.camera = Vec2i(0,0); 
                 state.level = 2;
                 state.mode = Mode::EndGame;
+                state.contact_tracker.clear();
             }
             _ => {}
         }
@@ -10,11 +10,14 @@ use winit_input_helper::WinitInputHelper;
 use engine2d::{animation, texture, types::*};
 use engine2d::graphics::Screen;
 use engine2d::tiles::*;
+use engine2d::level::Spawn;
 use engine2d::animation::*;
 use engine2d::text::{self, DrawText};
 use engine2d::sprite::*;
 
 use engine2d::collision::*;
+use engine2d::physics::{Body, sync_bodies};
+use engine2d::controller::{TopDownController, arrow_axis};
 // Imagine a Resources struct (we'll call it AssetDB or Assets in the future)
 // which wraps all accesses to textures, sounds, animations, etc.
 use engine2d::resources::*;
@@ -38,7 +41,7 @@ enum EntityType {
 
 }
 
-type Level = (Vec<Tilemap>, Vec<(EntityType, i32, i32)>);
+type Level = (Vec<Tilemap>, Vec<Spawn<EntityType>>);
 type Input = WinitInputHelper;
 
 #[derive(Debug,Clone,Copy)]
@@ -48,14 +51,6 @@ enum Mode {
     Lvl2,
     EndGame
 }
-#[derive(Debug,Clone,Copy)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
 impl Mode {
     // update consumes self and yields a new state (which might also just be self)
     fn update(self, game:&mut GameState, input:&Input,levels: &Vec<Level>) -> Self {
@@ -69,61 +64,7 @@ impl Mode {
                 }
             },
             Mode::Lvl1 => {
-                if game.movable {                    
-                    if input.key_held(VirtualKeyCode::Right) {
-                        game.anim_state[3].change_time(3);
-                        if game.positions[3].0 < 1000 {
-                            game.velocities[3].0 = 1;
-                        } else {
-                            game.velocities[3].0 = 0;
-                        }
-                        if game.positions[3].0 >= game.camera.0+16*5 && 
-                            game.camera.0 < 256 {
-                            game.camera.0 += 1;
-                        }
-                    } else if input.key_held(VirtualKeyCode::Left) {
-                        game.anim_state[3].change_time(2);
-                        if game.positions[3].0 > -256 {
-                            game.velocities[3].0 = -1;
-                        } else {
-                            game.velocities[3].0 = 0;
-                        }
-                        if game.positions[3].0 <= game.camera.0+16*5 && 
-                            game.camera.0 > -256 {
-                            game.camera.0 -= 1;
-                        }
-                    } else {
-                        game.velocities[3].0 = 0;
-                    }
-                    if input.key_held(VirtualKeyCode::Up) {
-                        game.anim_state[3].change_time(0);
-                        if game.positions[3].1 > -256 {
-                            game.velocities[3].1 = -1;
-                        } else {
-                            game.velocities[3].1 = 0;
-                        }
-                        if game.positions[3].1 <= game.camera.1+16*5 && 
-                            game.camera.1 > -256 {
-                            game.camera.1 -= 1;
-                        }
-                        
-                    } else if input.key_held(VirtualKeyCode::Down) {
-                        game.anim_state[3].change_time(1);
-                        if game.positions[3].1 < 208 {
-                            game.velocities[3].1 = 1;
-                        } else {
-                            game.velocities[3].1 = 0;
-                        }                     
-                        if game.positions[3].1 >= game.camera.1+16*5 && 
-                            game.camera.1 < 0 {
-                            game.camera.1 += 1;
-                        }
-                    } else {
-                        game.velocities[3].1 = 0;
-                    }
-                } else { // on not movable
-                    game.velocities[3] = Vec2i(0,0);
-                }
+                move_player(game, input, levels, Rect::new(-256, -256, 1256+16, 464+16), (Vec2i(-256, -256), Vec2i(256, 0)));
                 for (posn, vel) in game.positions.iter_mut().zip(game.velocities.iter()) {
                     posn.0 += vel.0;
                     posn.1 += vel.1;
@@ -144,62 +85,7 @@ impl Mode {
 
             },
             Mode::Lvl2 => {
-                if game.movable {                    
-                    if input.key_held(VirtualKeyCode::Right) {
-                        if game.positions[3].0 < 2000 {
-                            game.anim_state[3].change_time(3);
-                            game.velocities[3].0 = 1;
-                        } else {
-                            game.velocities[3].0 = 0;
-                        }
-                        if game.positions[3].0 >= game.camera.0+16*5 && 
-                            game.camera.0 < 512 {
-                            game.camera.0 += 1;
-                        }
-                    } else if input.key_held(VirtualKeyCode::Left) {
-                        game.anim_state[3].change_time(2);
-                        if game.positions[3].0 > 48
-                         {
-                            game.velocities[3].0 = -1;
-                        } else {
-                            game.velocities[3].0 = 0;
-                        }
-                        if game.positions[3].0 <= game.camera.0+16*5 && 
-                            game.camera.0 > 0 {
-                            game.camera.0 -= 1;
-                        }
-                    } else {
-                        game.velocities[3].0 = 0;
-                    }
-                    if input.key_held(VirtualKeyCode::Up) {
-                        game.anim_state[3].change_time(0);
-                        if game.positions[3].1 > 0 {
-                            game.velocities[3].1 = -1;
-                        } else {
-                            game.velocities[3].1 = 0;
-                        }
-                        if game.positions[3].1 <= game.camera.1+16*5 && 
-                            game.camera.1 > 0 {
-                            game.camera.1 -= 1;
-                        }
-                    } else if input.key_held(VirtualKeyCode::Down) {
-                        game.anim_state[3].change_time(1);
-                        if game.positions[3].1 < 512 {
-                            game.velocities[3].1 = 1;
-                        } else {
-                            game.velocities[3].1 = 0;
-                        }
-                    
-                        if game.positions[3].1 >= game.camera.1+16*5 && 
-                            game.camera.1 < 256 {
-                            game.camera.1 += 1;
-                        }
-                    } else {
-                        game.velocities[3].1 = 0;
-                    }
-                } else { // on not movable
-                    game.velocities[3] = Vec2i(0,0);
-                }
+                move_player(game, input, levels, Rect::new(48, 0, 1952+16, 512+16), (Vec2i(0, 0), Vec2i(512, 256)));
                 for (posn, vel) in game.positions.iter_mut().zip(game.velocities.iter()) {
                     posn.0 += vel.0;
                     posn.1 += vel.1;
@@ -219,13 +105,13 @@ impl Mode {
             }
         }
     }
-    fn display(&self, game:&GameState, screen: &mut Screen, levels: &Vec<Level>, rsrc:&Resources) {
+    fn display(&self, game:&GameState, screen: &mut Screen, levels: &Vec<Level>, rsrc:&Resources, frame:usize) {
         match self {
             Mode::Title => {
                 // let rsrc = Resources::new();
     
                 for t in levels[0].0.iter(){
-                    t.draw(screen);
+                    t.draw(screen, frame);
                 }
                 screen.draw_text(
                     "finding home",
@@ -248,7 +134,7 @@ impl Mode {
             Mode::Lvl1=> {
                 screen.set_scroll(game.camera);
                 for t in levels[1].0.iter(){
-                    t.draw(screen);
+                    t.draw(screen, frame);
                 }
                 for ((pos,tex),anim) in game.positions.iter().zip(game.textures.iter()).zip(game.anim_state.iter()) {
                     // screen.bitblt(tex,anim.frame(),*pos);
@@ -264,7 +150,7 @@ impl Mode {
             Mode::Lvl2=> {
                 screen.set_scroll(game.camera);
                 for t in levels[2].0.iter(){
-                    t.draw(screen);
+                    t.draw(screen, frame);
                 }
     
                 for ((mut pos,mut tex),mut anim) in game.positions.iter().zip(game.textures.iter()).zip(game.anim_state.iter()) {
@@ -273,7 +159,7 @@ impl Mode {
             },
             Mode::EndGame => {
                 for t in levels[3].0.iter(){
-                    t.draw(screen);
+                    t.draw(screen, frame);
                 }
                 screen.draw_text(
                     "you are home",
@@ -286,6 +172,38 @@ impl Mode {
 }
 
 
+// Walk the player (entity 3) around inside bounds, scrolling the camera within camera_range to follow
+fn move_player(game:&mut GameState, input:&Input, levels:&[Level], bounds:Rect, camera_range:(Vec2i, Vec2i)) {
+    if !game.movable {
+        game.player_body.velocity = Vec2f(0.0, 0.0);
+        return;
+    }
+    let axis = arrow_axis(input);
+    sync_bodies(std::slice::from_mut(&mut game.player_body), &game.positions[3..4]);
+    game.player_control.bounds = Some(bounds);
+    game.player_control.update(&mut game.player_body, game.sizes[3], axis, &levels[game.level].0);
+    game.positions[3] = game.player_body.pixel();
+    if axis != Vec2f(0.0, 0.0) {
+        game.anim_state[3].change_time(match game.player_control.facing {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        });
+    }
+    let (lo, hi) = camera_range;
+    if axis.0 > 0.0 && game.positions[3].0 >= game.camera.0+16*5 && game.camera.0 < hi.0 {
+        game.camera.0 += 1;
+    } else if axis.0 < 0.0 && game.positions[3].0 <= game.camera.0+16*5 && game.camera.0 > lo.0 {
+        game.camera.0 -= 1;
+    }
+    if axis.1 < 0.0 && game.positions[3].1 <= game.camera.1+16*5 && game.camera.1 > lo.1 {
+        game.camera.1 -= 1;
+    } else if axis.1 > 0.0 && game.positions[3].1 >= game.camera.1+16*5 && game.camera.1 < hi.1 {
+        game.camera.1 += 1;
+    }
+}
+
 struct GameState{
         // Every entity has a position, a size, a texture, and animation state.
         // Assume entity 0 is the player
@@ -293,12 +211,17 @@ struct GameState{
         positions: Vec<Vec2i>,
         velocities: Vec<Vec2i>,
         sizes:Vec<(usize,usize)>,
+        // Everything in this game is a trigger; the walls are tiles
+        colliders:Vec<Collider>,
         textures:Vec<Rc<Texture>>,
         anim_state:Vec<AnimationState>,
         // Current level
         level:usize,
         // Camera position
         camera:Vec2i,
+        // The player moves through its body, so it keeps sub-pixel positions
+        player_body:Body,
+        player_control:TopDownController,
         mode:Mode,
         movable:bool,
         soundstream: (rodio::OutputStream, rodio::OutputStreamHandle),
@@ -317,59 +240,59 @@ fn main() {
     let rsrc = Resources::new();
     let hometileset = Rc::new(Tileset::new(
         vec![
-            Tile{solid:false}, Tile{solid:true}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false},
+            Tile::OPEN, Tile::SOLID, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN,
         ],
         &rsrc.load_texture(Path::new("content/home.png"))
     ));
     let lvl1tileset = Rc::new(Tileset::new(
         vec![
-            Tile{solid:false}, Tile{solid:true}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:true}, Tile{solid:false}, Tile{solid:true}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:true}, Tile{solid:true}, Tile{solid:true}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, 
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false},
+            Tile::OPEN, Tile::SOLID, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::SOLID, Tile::OPEN, Tile::SOLID, Tile::OPEN, Tile::OPEN,
+            Tile::SOLID, Tile::SOLID, Tile::SOLID, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, 
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN,
         ],
         &rsrc.load_texture(Path::new("content/lvl1.png"))
     ));
     let lvl2tileset = Rc::new(Tileset::new(
         vec![
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},//5
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},//15
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},//25
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},//35
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false}, Tile{solid:false},//45
-            Tile{solid:false}, Tile{solid:false}, Tile{solid:true}, Tile{solid:false}, Tile{solid:false},//50
-            Tile{solid:false}, Tile{solid:true}, Tile{solid:false}, Tile{solid:false}, Tile{solid:true},//55
-            Tile{solid:true}, Tile{solid:true}, Tile{solid:true}, Tile{solid:true}, Tile{solid:true},
-            Tile{solid:true}, Tile{solid:true}, Tile{solid:true}, Tile{solid:true},
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,//5
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,//15
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,//25
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,//35
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,
+            Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN, Tile::OPEN,//45
+            Tile::OPEN, Tile::OPEN, Tile::SOLID, Tile::OPEN, Tile::OPEN,//50
+            Tile::OPEN, Tile::SOLID, Tile::OPEN, Tile::OPEN, Tile::SOLID,//55
+            Tile::SOLID, Tile::SOLID, Tile::SOLID, Tile::SOLID, Tile::SOLID,
+            Tile::SOLID, Tile::SOLID, Tile::SOLID, Tile::SOLID,
             
         ],
         &rsrc.load_texture(Path::new("content/lvl2.png"))
@@ -725,18 +648,18 @@ fn main() {
 
         (vec![lvl1map_1,lvl1map_2,lvl1map_3,lvl1map_4,lvl1map_5,lvl1map_6],
          vec![
-            (EntityType::Barrier, 17,-12),
-            (EntityType::lvl1Exit, 27,-16),
-            (EntityType::Enemy, 20, 20),
-            (EntityType::Player, 8, 13),
+            Spawn::new(EntityType::Barrier, Vec2i(17*16, -12*16)),
+            Spawn::new(EntityType::lvl1Exit, Vec2i(27*16, -16*16)),
+            Spawn::new(EntityType::Enemy, Vec2i(20*16, 20*16)),
+            Spawn::new(EntityType::Player, Vec2i(8*16, 13*16)),
             ]
         ), 
         (vec![lvl2_map1,lvl2_map2,lvl2_map3,lvl2_map4,lvl2_map5,lvl2_map6],  
               vec![
-                (EntityType::lvl2Entrance, 45, 10),
-                (EntityType::lvl2Exit, 650,10),
-                (EntityType::Bridge, 320,220),
-                (EntityType::Player, 45, 15)
+                Spawn::new(EntityType::lvl2Entrance, Vec2i(45, 10)),
+                Spawn::new(EntityType::lvl2Exit, Vec2i(650, 10)),
+                Spawn::new(EntityType::Bridge, Vec2i(320, 220)),
+                Spawn::new(EntityType::Player, Vec2i(45, 15))
               ]
             
         ),
@@ -774,31 +697,20 @@ fn main() {
         // Assume entity 0 is the player
         types: vec![
             // In a real example we'd provide nicer accessors than this
-            levels[1].1[0].0,
-            levels[1].1[1].0,
-            levels[1].1[2].0,
-            levels[1].1[3].0,
+            levels[1].1[0].kind,
+            levels[1].1[1].kind,
+            levels[1].1[2].kind,
+            levels[1].1[3].kind,
         ],
         positions: vec![
-            Vec2i(
-                levels[1].1[0].1 * 16,
-                levels[1].1[0].2 * 16,
-            ),
-            Vec2i(
-                levels[1].1[1].1 * 16,
-                levels[1].1[1].2 * 16,
-            ),
-            Vec2i(
-                levels[1].1[2].1 * 16,
-                levels[1].1[2].2 * 16,
-            ),
-            Vec2i(
-                levels[1].1[3].1 * 16,
-                levels[1].1[3].2 * 16,
-            ),
+            levels[1].1[0].position,
+            levels[1].1[1].position,
+            levels[1].1[2].position,
+            levels[1].1[3].position,
         ],
         velocities: vec![Vec2i(0,0), Vec2i(0,0),Vec2i(0,0), Vec2i(0,0),Vec2i(0,0),Vec2i(0,0), Vec2i(0,0)],
         sizes: vec![(16,16), (40,26),(16,16), (16,16),(16,16), (16,16),(16,16)],
+        colliders: vec![Collider::trigger(1, u32::MAX); 4],
         // Could be texture handles instead, let's talk about that in two weeks
         textures: vec![Rc::clone(&barrier_tex),
                        Rc::clone(&lvl1exit_tex),
@@ -810,6 +722,9 @@ fn main() {
         level: 0,
         // Camera position
         camera: Vec2i(0, 0),
+        player_body: Body { gravity_scale: 0.0, ..Body::new(levels[1].1[3].position) },
+        // A pixel a tick
+        player_control: TopDownController::new(60.0),
         mode:Mode::Title,
         movable:true,
         soundstream: OutputStream::try_default().unwrap()
@@ -824,7 +739,7 @@ fn main() {
 fn draw_game(resources:&Resources, levels: &Vec<Level>, state: &GameState, screen: &mut Screen, frame:usize) {
     screen.clear(Rgba(80, 80, 80, 255));
     screen.set_scroll(state.camera);
-    state.mode.display(state, screen,levels,resources);
+    state.mode.display(state, screen,levels,resources,frame);
 }
 
 fn update_game(resources:&Resources, levels: &Vec<Level>, state: &mut GameState, input: &WinitInputHelper, frame: usize) {
@@ -834,11 +749,13 @@ fn update_game(resources:&Resources, levels: &Vec<Level>, state: &mut GameState,
 
     // Handle collisions: Apply restitution impulses.
     //contacts.clear();
-    let contacts = engine2d::collision::gather_contacts(&state.positions, &state.sizes);
+    let contacts = engine2d::collision::gather_contacts(&state.positions, &state.sizes, &state.colliders);
     for contact in contacts.iter(){
         // println!("{}",state.level);
         // println!("before{:?}",state.positions.len());
-        match (state.types[contact.a],state.types[contact.b]){
+        // Each pair only comes back once, so put the player first
+        let (a, b) = if state.types[contact.b] == EntityType::Player { (contact.b, contact.a) } else { (contact.a, contact.b) };
+        match (state.types[a],state.types[b]){
             (EntityType::Player, EntityType::Barrier) => {
                 state.movable = false;
                 state.anim_state[0].change_time(1);
@@ -849,15 +766,17 @@ fn update_game(resources:&Resources, levels: &Vec<Level>, state: &mut GameState,
                 state.camera=Vec2i(0,0);
                 state.types.clear();
                 state.velocities.clear();
+                state.colliders.clear();
                 state.textures.clear();
                 state.sizes.clear();
                 state.positions.clear();
                 state.anim_state.clear();
                 // println!("after{:?}",state.positions.len());
-                for (e_type,x,y) in levels[2].1.iter(){
+                for Spawn{kind: e_type, position, ..} in levels[2].1.iter(){
                     state.types.push(*e_type);
-                    state.positions.push(Vec2i(*x,*y));
+                    state.positions.push(*position);
                     state.velocities.push(Vec2i(0,0));
+                    state.colliders.push(Collider::trigger(1, u32::MAX));
                     match e_type {
                         EntityType::Enemy => {}
                         EntityType::Barrier => {}
@@ -902,8 +821,9 @@ fn update_game(resources:&Resources, levels: &Vec<Level>, state: &mut GameState,
         }       
     }
 
-    let mut tile_contacts= engine2d::collision::gather_contacts_tilemap(&state.positions, &state.sizes,&levels[state.level].0);
-    engine2d::collision::restitute(&mut state.positions, &mut state.sizes,&mut tile_contacts);
+    // Everything here is a trigger, so nothing gets pushed out of the walls; the player's controller keeps out of them itself
+    let mut tile_contacts= engine2d::collision::gather_contacts_tilemap_shaped(&state.positions, &state.sizes, &state.colliders, &levels[state.level].0, u32::MAX);
+    engine2d::collision::restitute_shaped(&mut state.positions, &state.sizes, &state.colliders, &mut tile_contacts);
     println!("entered lvl1 collision");
     println!("{:?}",state.level);
 
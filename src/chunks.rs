@@ -0,0 +1,138 @@
+use crate::graphics::Screen;
+use crate::tiles::*;
+use crate::types::*;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Which chunk, counting from the chunk whose top-left corner is at the world origin
+pub type ChunkCoord = (i32, i32);
+
+/// Makes the tiles for a chunk the first time it's needed, e.g. by generating them or reading them from disk.
+/// It must return chunk width * chunk height row-major tile IDs (`TileID::EMPTY` is allowed).
+pub type ChunkLoader = Box<dyn FnMut(ChunkCoord) -> Vec<TileID>>;
+
+/// A world too big for one Tilemap, made of same-sized tilemap chunks that are loaded around the camera
+/// and thrown away again once they're far from it. Chunk (0, 0) starts at the world origin and chunks
+/// extend in every direction, so negative coordinates work too.
+pub struct ChunkedTilemap {
+    tileset: Rc<Tileset>,
+    /// How many tiles across and down each chunk is
+    chunk_dims: (usize, usize),
+    chunks: HashMap<ChunkCoord, Tilemap>,
+    loader: ChunkLoader,
+    /// Chunks within this many chunks of the camera get loaded
+    pub load_margin: i32,
+    /// Chunks further than this many chunks from the camera get evicted.
+    /// Keep it bigger than `load_margin` so walking back and forth over a seam doesn't reload chunks.
+    pub evict_margin: i32,
+}
+impl ChunkedTilemap {
+    pub fn new(tileset: &Rc<Tileset>, chunk_dims: (usize, usize), loader: ChunkLoader) -> Self {
+        assert!(chunk_dims.0 > 0 && chunk_dims.1 > 0, "Chunks must have a size");
+        Self {
+            tileset: Rc::clone(tileset),
+            chunk_dims,
+            chunks: HashMap::new(),
+            loader,
+            load_margin: 1,
+            evict_margin: 2,
+        }
+    }
+    pub fn chunk_dims(&self) -> (usize, usize) {
+        self.chunk_dims
+    }
+    /// The width and height of one chunk in pixels
+    pub fn chunk_size_px(&self) -> (i32, i32) {
+        let (tw, th) = self.tileset.tile_size();
+        (
+            (self.chunk_dims.0 * tw) as i32,
+            (self.chunk_dims.1 * th) as i32,
+        )
+    }
+    /// The chunk containing world position posn
+    pub fn chunk_coord(&self, Vec2i(x, y): Vec2i) -> ChunkCoord {
+        let (cw, ch) = self.chunk_size_px();
+        // Round down rather than towards zero, so -1 is in chunk -1 and not chunk 0
+        (x.div_euclid(cw), y.div_euclid(ch))
+    }
+    /// The world position of a chunk's top-left corner
+    pub fn chunk_origin(&self, (cx, cy): ChunkCoord) -> Vec2i {
+        let (cw, ch) = self.chunk_size_px();
+        Vec2i(cx * cw, cy * ch)
+    }
+    pub fn chunk(&self, coord: ChunkCoord) -> Option<&Tilemap> {
+        self.chunks.get(&coord)
+    }
+    /// The currently loaded chunks, in no particular order
+    pub fn loaded(&self) -> impl Iterator<Item = (ChunkCoord, &Tilemap)> {
+        self.chunks.iter().map(|(c, tm)| (*c, tm))
+    }
+    /// Get a chunk, loading it first if needed.
+    /// Chunks are only handed out to read, so edits go through `set_tile` and they never move off their spot.
+    pub fn load(&mut self, coord: ChunkCoord) -> &Tilemap {
+        if !self.chunks.contains_key(&coord) {
+            let ids = (self.loader)(coord);
+            let tm = Tilemap::from_ids(self.chunk_origin(coord), self.chunk_dims, &self.tileset, ids);
+            self.chunks.insert(coord, tm);
+        }
+        &self.chunks[&coord]
+    }
+    /// Replace the tile at world position posn, loading its chunk first if needed, and return the one that was there
+    pub fn set_tile(&mut self, posn: Vec2i, id: TileID) -> Result<TileID, TileError> {
+        let coord = self.chunk_coord(posn);
+        self.load(coord);
+        let tm = self.chunks.get_mut(&coord).unwrap();
+        let TileCoord(x, y) = tm.tile_coord(posn);
+        tm.set_tile(x as usize, y as usize, id)
+    }
+    /// Load every chunk near the camera and evict the ones far from it.
+    /// The evicted chunks are handed back so edits to them can be saved.
+    pub fn update(&mut self, camera: Rect) -> Vec<(ChunkCoord, Tilemap)> {
+        let ((x0, y0), (x1, y1)) = self.chunk_span(camera);
+        for cy in (y0 - self.load_margin)..=(y1 + self.load_margin) {
+            for cx in (x0 - self.load_margin)..=(x1 + self.load_margin) {
+                self.load((cx, cy));
+            }
+        }
+        let m = self.evict_margin.max(self.load_margin);
+        let far: Vec<ChunkCoord> = self
+            .chunks
+            .keys()
+            .filter(|(cx, cy)| *cx < x0 - m || *cx > x1 + m || *cy < y0 - m || *cy > y1 + m)
+            .copied()
+            .collect();
+        far.into_iter()
+            .map(|c| (c, self.chunks.remove(&c).unwrap()))
+            .collect()
+    }
+    /// Which tile is at world position posn, if its chunk is loaded
+    pub fn tile_id_at(&self, posn: Vec2i) -> Option<(TileID, Rect)> {
+        self.chunk(self.chunk_coord(posn))
+            .and_then(|tm| tm.tile_id_at(posn))
+    }
+    pub fn tile_at(&self, posn: Vec2i) -> Option<(Tile, Rect)> {
+        self.chunk(self.chunk_coord(posn))
+            .and_then(|tm| tm.tile_at(posn))
+    }
+    /// Draw the loaded chunks that appear on screen
+    pub fn draw(&self, screen: &mut Screen, tick: usize) {
+        let ((x0, y0), (x1, y1)) = self.chunk_span(screen.bounds());
+        for cy in y0..=y1 {
+            for cx in x0..=x1 {
+                if let Some(tm) = self.chunk((cx, cy)) {
+                    tm.draw(screen, tick);
+                }
+            }
+        }
+    }
+    /// The first and last chunks overlapped by r
+    fn chunk_span(&self, r: Rect) -> (ChunkCoord, ChunkCoord) {
+        let first = self.chunk_coord(Vec2i(r.x, r.y));
+        let last = self.chunk_coord(Vec2i(
+            r.x + (r.w as i32 - 1).max(0),
+            r.y + (r.h as i32 - 1).max(0),
+        ));
+        (first, last)
+    }
+}
@@ -4,6 +4,11 @@ use crate::types::*;
 use std::rc::Rc; 
 use crate::animation::*;
 use crate::tiles::*;
+use crate::layers::LayeredTilemap;
+use crate::chunks::ChunkedTilemap;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use crate::shapes::*;
 
 // impl Contact {
 //     pub fn get_ids(&self) -> (ContactID, ContactID) {
@@ -11,24 +16,162 @@ use crate::tiles::*;
 //     }
 // }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct TileContact{
     pub tile: Tile,
-    pub rect: Rect  
+    pub rect: Rect,
+    /// Which tilemap the tiles are in: its index in the slice contacts were gathered from,
+    /// or among a layered map's colliding layers
+    pub map: usize,
+}
+// Tile contacts are told apart by their map and rect, so a `ContactTracker` can follow them from tick to tick.
+// Merged rects cover the whole block of alike tiles, wherever the entity touches it, so walking along
+// a floor or a strip of hazards is one contact the whole way.
+impl PartialEq for TileContact {
+    fn eq(&self, other: &Self) -> bool {
+        (self.map, self.rect) == (other.map, other.rect)
+    }
+}
+impl Eq for TileContact {}
+impl Hash for TileContact {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.map, self.rect).hash(state);
+    }
 }
 // pub enum ContactID {
 //     Barrier,
 //     Player,
 // }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Contact<A,B>{
     pub a: A, 
     pub b: B,
-    pub mtv:(i32,i32)
+    /// How far to move `a` so it no longer overlaps `b`, rounded to whole pixels.
+    /// Between rects this is along whichever axis needs the smaller push.
+    pub mtv:(i32,i32),
+    /// Which way to push `a`, as a unit vector: e.g. (0, -1) when `a` is resting on top of `b`
+    pub normal:Vec2f,
+    /// How far `a` is into `b`; 0 if they're only touching
+    pub depth:f32,
+    /// Whether either side is a trigger, so the overlap is only reported and nothing gets pushed
+    pub trigger:bool,
+}
+impl<A,B> Contact<A,B> {
+    /// The contact between `a` at r1 and `b` at r2, if the rects overlap or touch
+    pub fn between(a:A, r1:Rect, b:B, r2:Rect) -> Option<Self> {
+        rect_mtv(r1, r2).map(|(normal, depth)| Contact {
+            a,
+            b,
+            mtv: (normal.0 * depth, normal.1 * depth),
+            normal: Vec2f(normal.0 as f32, normal.1 as f32),
+            depth: depth as f32,
+            trigger: false,
+        })
+    }
+    /// A contact from a normal and depth worked out in fractional pixels
+    fn pushing(a:A, b:B, normal:Vec2f, depth:f32) -> Self {
+        Contact {
+            a,
+            b,
+            mtv: ((normal.0 * depth).round() as i32, (normal.1 * depth).round() as i32),
+            normal,
+            depth,
+            trigger: false,
+        }
+    }
+}
+
+/// Which other colliders an entity collides with, and whether it's solid
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Collider {
+    /// The layers this collider is on, one per bit
+    pub layer: u32,
+    /// The layers it collides with
+    pub mask: u32,
+    /// Triggers report overlaps, like level exits or pickups, but never push or get pushed
+    pub trigger: bool,
+    /// What fills the entity's box; rects unless set otherwise
+    pub shape: Shape,
+}
+impl Collider {
+    pub fn new(layer: u32, mask: u32) -> Self {
+        Self { layer, mask, trigger: false, shape: Shape::Rect }
+    }
+    pub fn trigger(layer: u32, mask: u32) -> Self {
+        Self { layer, mask, trigger: true, shape: Shape::Rect }
+    }
+    /// Two colliders only interact if each one's mask includes the other's layer
+    pub fn interacts(&self, other: &Collider) -> bool {
+        self.mask & other.layer != 0 && other.mask & self.layer != 0
+    }
+}
+impl Default for Collider {
+    /// Solid, on the first layer, and colliding with everything
+    fn default() -> Self {
+        Self::new(1, u32::MAX)
+    }
 }
 
 // pub fn gather_contacts(player:, Barrier:) -> Vec<Contact> {}
+
+/// Where a contact is in its lifetime, from one tick to the next
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContactPhase {
+    /// The pair wasn't in contact last tick
+    Began,
+    /// The pair was in contact last tick too
+    Persisted,
+    /// The pair was in contact last tick but isn't now
+    Ended,
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ContactEvent<A,B> {
+    pub phase: ContactPhase,
+    /// This tick's contact, or for `Ended` the last one seen
+    pub contact: Contact<A,B>,
+}
+
+/// Remembers last tick's contacts so this tick's can be turned into began/persisted/ended events.
+/// Contacts are matched up by their (a, b) pair.
+pub struct ContactTracker<A,B> {
+    previous: Vec<Contact<A,B>>,
+}
+impl<A: Copy + Eq + Hash, B: Copy + Eq + Hash> ContactTracker<A,B> {
+    pub fn new() -> Self {
+        Self { previous: vec![] }
+    }
+    /// Compare this tick's contacts against last tick's. Events come in the order of `contacts`,
+    /// followed by the ended ones in the order they were last seen.
+    pub fn update(&mut self, contacts: &[Contact<A,B>]) -> Vec<ContactEvent<A,B>> {
+        let before: HashSet<(A,B)> = self.previous.iter().map(|c| (c.a, c.b)).collect();
+        let now: HashSet<(A,B)> = contacts.iter().map(|c| (c.a, c.b)).collect();
+        let mut events: Vec<ContactEvent<A,B>> = contacts
+            .iter()
+            .map(|&contact| ContactEvent {
+                phase: if before.contains(&(contact.a, contact.b)) { ContactPhase::Persisted } else { ContactPhase::Began },
+                contact,
+            })
+            .collect();
+        events.extend(
+            self.previous
+                .iter()
+                .filter(|c| !now.contains(&(c.a, c.b)))
+                .map(|&contact| ContactEvent { phase: ContactPhase::Ended, contact }),
+        );
+        self.previous = contacts.to_vec();
+        events
+    }
+    /// Forget every contact without ending them, e.g. when the entities are all replaced on a level change
+    pub fn clear(&mut self) {
+        self.previous.clear();
+    }
+}
+impl<A: Copy + Eq + Hash, B: Copy + Eq + Hash> Default for ContactTracker<A,B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
     
 pub fn rect_touching(r1:Rect, r2:Rect) -> bool {
     // r1 left is left of r2 right
@@ -56,96 +199,588 @@ pub fn rect_displacement(r1:Rect, r2:Rect) -> Option<(i32,i32)> {
     }
 }
 
+/// Which way and how far to push r1 out of r2, if the rects overlap or touch: a unit normal along the axis
+/// where they overlap least, and the overlap on that axis. The signed push is normal * depth.
+/// r1 is pushed away from r2's center; when the centers line up it goes left or up.
+pub fn rect_mtv(r1:Rect, r2:Rect) -> Option<((i32,i32), i32)> {
+    let (x, y) = rect_displacement(r1, r2)?;
+    // Doubled centers, so odd sizes don't round
+    let dx = (2 * r1.x + r1.w as i32) - (2 * r2.x + r2.w as i32);
+    let dy = (2 * r1.y + r1.h as i32) - (2 * r2.y + r2.h as i32);
+    let normal = if x > y {
+        (0, if dy > 0 { 1 } else { -1 })
+    } else {
+        (if dx > 0 { 1 } else { -1 }, 0)
+    };
+    Some((normal, x.min(y)))
+}
+
 // pub fn resources_displacement(r1:Rc<Animation>, r2:Rc<Animation>) -> Option<(i32,i32)> {
 //     return rect_displacement(r1.frames[0].0, r2.frames[0].0);
 // }
 
-pub fn gather_contacts(positions: &Vec<Vec2i>, sizes: &Vec<(usize,usize)>) -> Vec<Contact<usize,usize>> {
+const BRUTE_FORCE_LIMIT: usize = 64;
+/// Every pair of entities whose rects overlap or touch and whose colliders interact, each pair once with a < b.
+/// This uses a `SpatialHash` broadphase sized to the entities, so it stays fast with thousands of them.
+pub fn gather_contacts(positions: &[Vec2i], sizes: &[(usize,usize)], colliders: &[Collider]) -> Vec<Contact<usize,usize>> {
+    // Below this many entities, building the hash costs more than it saves (see benches/broadphase.rs)
+    if positions.len() < BRUTE_FORCE_LIMIT {
+        return gather_contacts_brute(positions, sizes, colliders);
+    }
+    // Cells about twice the size of a typical entity keep most entities in one to four cells
+    let typical = sizes.iter().map(|(w, h)| (*w).max(*h)).sum::<usize>() / sizes.len();
+    gather_contacts_grid(positions, sizes, colliders, (typical * 2).max(8))
+}
+/// Like `gather_contacts`, with a chosen spatial hash cell size in pixels
+pub fn gather_contacts_grid(positions: &[Vec2i], sizes: &[(usize,usize)], colliders: &[Collider], cell_size: usize) -> Vec<Contact<usize,usize>> {
+    let mut grid = SpatialHash::new(cell_size);
+    for (i, (p, s)) in positions.iter().zip(sizes.iter()).enumerate() {
+        grid.insert(i, entity_rect(*p, *s));
+    }
+    grid.pairs()
+        .into_iter()
+        .filter(|&(a, b)| colliders[a].interacts(&colliders[b]))
+        .filter_map(|(a, b)| entity_contact(a, grid.rects[a], b, grid.rects[b], colliders))
+        .collect()
+}
+/// The same result as `gather_contacts` by testing every pair, for comparison and for very small scenes
+pub fn gather_contacts_brute(positions: &[Vec2i], sizes: &[(usize,usize)], colliders: &[Collider]) -> Vec<Contact<usize,usize>> {
     let mut into = vec![];
     for i in 0..positions.len() {
-        let mut rect1 = Rect {
-            x: positions[i].0,
-            y: positions[i].1,
-            w: sizes[i].0 as u16,
-            h: sizes[i].1 as u16,
-        };
-        for j in 0..positions.len() {
-            if j==i {
+        let rect1 = entity_rect(positions[i], sizes[i]);
+        for j in i + 1..positions.len() {
+            if !colliders[i].interacts(&colliders[j]) {
                 continue;
-            } else {
-                let mut rect2 = Rect {
-                    x: positions[j].0,
-                    y: positions[j].1,
-                    w: sizes[j].0 as u16,
-                    h: sizes[j].1 as u16,
-                };
-                if let Some(disp) = rect_displacement(rect1, rect2) {
-                    into.push(Contact{a:i, b:j, mtv:disp});
-                }
             }
+            let rect2 = entity_rect(positions[j], sizes[j]);
+            into.extend(entity_contact(i, rect1, j, rect2, colliders));
         }
     }
-    return into;
+    into
 }
-// Loop through tiles that might be touching
-pub fn gather_contacts_tilemap(positions: &Vec<Vec2i>, sizes: &Vec<(usize,usize)>,tilemaps:&Vec<Tilemap>)-> Vec<Contact<usize,TileContact>> {
-    let mut into = vec![];
-    for (i, (pos, sz)) in positions.iter().zip(sizes.iter()).enumerate() {
-        let mut rect1 = Rect {
-            x: positions[i].0,
-            y: positions[i].1,
-            w: sizes[i].0 as u16,
-            h: sizes[i].1 as u16,
-        };
-        for (tmi,tm) in tilemaps.iter().enumerate(){
-            for (tile,rect) in [Vec2i(positions[i].0,positions[i].1), //x,y
-                                          Vec2i(positions[i].0+sizes[i].0 as i32,positions[i].1), //x+w,y
-                                          Vec2i(positions[i].0,positions[i].1+sizes[i].1 as i32), //x,y+h
-                                          Vec2i(positions[i].0+sizes[i].0 as i32,positions[i].1+sizes[i].1 as i32)].iter().filter_map(|pos| tm.tile_at(*pos)){
-                if tile.solid{
-                    if let Some(disp)=rect_displacement(rect1, rect){
-                        into.push(Contact{a:i,b:TileContact { tile: tile, rect: rect },mtv:disp});
-                        
-                        
+fn entity_contact(a: usize, ra: Rect, b: usize, rb: Rect, colliders: &[Collider]) -> Option<Contact<usize,usize>> {
+    let (sa, sb) = (colliders[a].shape, colliders[b].shape);
+    let contact = if sa == Shape::Rect && sb == Shape::Rect {
+        Contact::between(a, ra, b, rb)
+    } else {
+        let corner = |r: Rect| Vec2f(r.x as f32, r.y as f32);
+        let size = |r: Rect| (r.w as usize, r.h as usize);
+        shape_overlap(sa, corner(ra), size(ra), sb, corner(rb), size(rb))
+            .map(|(normal, depth)| Contact::pushing(a, b, normal, depth))
+    };
+    contact.map(|c| Contact {
+        trigger: colliders[a].trigger || colliders[b].trigger,
+        ..c
+    })
+}
+fn entity_rect(Vec2i(x, y): Vec2i, (w, h): (usize, usize)) -> Rect {
+    Rect { x, y, w: w as u16, h: h as u16 }
+}
+
+/// A uniform grid broadphase: rects are bucketed by the grid cells they cover,
+/// so only rects sharing a cell are ever compared.
+pub struct SpatialHash {
+    cell_size: i32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    // Inserted rects, indexed by id (ids not inserted have empty rects and no cells)
+    rects: Vec<Rect>,
+}
+impl SpatialHash {
+    pub fn new(cell_size: usize) -> Self {
+        assert!(cell_size > 0, "Cells must have a size");
+        Self {
+            cell_size: cell_size as i32,
+            cells: HashMap::new(),
+            rects: vec![],
+        }
+    }
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.rects.clear();
+    }
+    /// Add a rect with the given id; ids are usually entity indices.
+    pub fn insert(&mut self, id: usize, rect: Rect) {
+        if self.rects.len() <= id {
+            self.rects.resize(id + 1, Rect::new(0, 0, 0, 0));
+        }
+        self.rects[id] = rect;
+        let ((x0, y0), (x1, y1)) = self.cell_span(rect);
+        for cy in y0..=y1 {
+            for cx in x0..=x1 {
+                self.cells.entry((cx, cy)).or_default().push(id);
+            }
+        }
+    }
+    /// Every pair of ids sharing at least one cell, once each with the smaller id first, in sorted order.
+    /// These might still not touch; check them with `rect_displacement`.
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        for (&(cx, cy), ids) in self.cells.iter() {
+            for (n, &a) in ids.iter().enumerate() {
+                let (amin, _) = self.cell_span(self.rects[a]);
+                for &b in ids[n + 1..].iter() {
+                    let (bmin, _) = self.cell_span(self.rects[b]);
+                    // Two rects can share several cells; only report them from the first (top-left) one
+                    if (amin.0.max(bmin.0), amin.1.max(bmin.1)) == (cx, cy) {
+                        pairs.push((a.min(b), a.max(b)));
                     }
                 }
-            }     
-
+            }
         }
+        // Cells come out in whatever order the HashMap likes, so sort for the same order every run
+        pairs.sort_unstable();
+        pairs
+    }
+    /// Ids of rects sharing a cell with r, each once
+    pub fn query(&self, r: Rect) -> Vec<usize> {
+        let ((x0, y0), (x1, y1)) = self.cell_span(r);
+        let mut found = vec![];
+        for cy in y0..=y1 {
+            for cx in x0..=x1 {
+                if let Some(ids) = self.cells.get(&(cx, cy)) {
+                    found.extend(ids.iter().copied());
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+    /// The first and last cells a rect covers, counting its right and bottom edges so touching rects share a cell
+    fn cell_span(&self, r: Rect) -> ((i32, i32), (i32, i32)) {
+        let c = self.cell_size;
+        (
+            (r.x.div_euclid(c), r.y.div_euclid(c)),
+            ((r.x + r.w as i32).div_euclid(c), (r.y + r.h as i32).div_euclid(c)),
+        )
     }
-    return into;
 }
+// Loop through tiles that might be touching.
+// Every entity counts as a solid rect here; entities with colliders want `gather_contacts_tilemap_shaped`.
+pub fn gather_contacts_tilemap(positions: &[Vec2i], sizes: &[(usize,usize)], tilemaps: &[Tilemap])-> Vec<Contact<usize,TileContact>> {
+    let mut into = vec![];
+    for (m, tm) in tilemaps.iter().enumerate() {
+        tilemap_contacts(positions, sizes, &|_| Collider::default(), u32::MAX, tm, m, &mut into);
+    }
+    into
+}
+/// Like `gather_contacts_tilemap`, but each entity collides as its collider says. Tiles are on `tile_layer`,
+/// so entities whose mask leaves it out get no tile contacts, and triggers only get trigger contacts, which
+/// `restitute_shaped` never pushes. Round shapes roll over seams and around corners instead of catching on them,
+/// though slopes and one-way tiles still collide with the entity's whole box.
+pub fn gather_contacts_tilemap_shaped(positions: &[Vec2i], sizes: &[(usize,usize)], colliders: &[Collider], tilemaps: &[Tilemap], tile_layer: u32) -> Vec<Contact<usize,TileContact>> {
+    assert_eq!(positions.len(), colliders.len());
+    let mut into = vec![];
+    for (m, tm) in tilemaps.iter().enumerate() {
+        tilemap_contacts(positions, sizes, &|i| colliders[i], tile_layer, tm, m, &mut into);
+    }
+    into
+}
+// Same, but against every layer of a layered map that takes part in collision
+pub fn gather_contacts_layered(positions: &[Vec2i], sizes: &[(usize,usize)], map: &LayeredTilemap) -> Vec<Contact<usize,TileContact>> {
+    let mut into = vec![];
+    for (m, tm) in map.colliding().enumerate() {
+        tilemap_contacts(positions, sizes, &|_| Collider::default(), u32::MAX, tm, m, &mut into);
+    }
+    into
+}
+// Same, but against the loaded chunks of a chunked world
+pub fn gather_contacts_chunked(positions: &[Vec2i], sizes: &[(usize,usize)], world: &ChunkedTilemap) -> Vec<Contact<usize,TileContact>> {
+    let mut into = vec![];
+    // Chunks never overlap, so their contacts' rects tell them apart and they can all be map 0
+    for (_coord, tm) in world.loaded() {
+        tilemap_contacts(positions, sizes, &|_| Collider::default(), u32::MAX, tm, 0, &mut into);
+    }
+    into
+}
+/// Contacts between each entity and the solid tiles its rect overlaps (just touching doesn't count).
+/// Hazard tiles which aren't solid come back as trigger contacts, and so does every tile a trigger touches.
+/// Neighbouring tiles that are exactly alike are merged into one rect first, so something sliding along
+/// a floor or wall isn't snagged on the seams between its tiles.
+fn tilemap_contacts(positions: &[Vec2i], sizes: &[(usize,usize)], collider_of: &dyn Fn(usize) -> Collider, tile_layer: u32, tm: &Tilemap, map: usize, into: &mut Vec<Contact<usize,TileContact>>) {
+    for i in 0..positions.len() {
+        let rect1 = entity_rect(positions[i], sizes[i]);
+        let collider = collider_of(i);
+        if sizes[i].0 == 0 || sizes[i].1 == 0 || collider.mask & tile_layer == 0 {
+            continue;
+        }
+        let shape = collider.shape;
+        let placed = shape.place(Vec2f(positions[i].0 as f32, positions[i].1 as f32), sizes[i]);
+        for contact in merged_tiles(tm, map, rect1) {
+            let found = if shape == Shape::Rect || contact.tile.slope != Slope::None || contact.tile.one_way {
+                Contact::between(i, rect1, contact, contact.rect)
+            } else {
+                round_tile_contact(tm, placed, contact).map(|(normal, depth)| Contact::pushing(i, contact, normal, depth))
+            };
+            into.extend(found.map(|c| Contact {
+                trigger: collider.trigger || !contact.tile.solid,
+                ..c
+            }));
+        }
+    }
+}
+// A round shape against a tile. Where it meets one of the tile's corners but the floor or wall carries on
+// past that corner, the corner isn't really there, so push straight off the face instead.
+fn round_tile_contact(tm: &Tilemap, shape: Placed, tc: TileContact) -> Option<(Vec2f, f32)> {
+    let tile_box = rect_box(tc.rect);
+    let (normal, depth) = overlap(shape, tile_box)?;
+    if normal.0 == 0.0 || normal.1 == 0.0 {
+        return Some((normal, depth));
+    }
+    let r = tc.rect;
+    // Pixels just past the corner sideways and up or down, and just inside it
+    let (out_x, in_x) = if normal.0 < 0.0 { (r.x - 1, r.x) } else { (r.x + r.w as i32, r.x + r.w as i32 - 1) };
+    let (out_y, in_y) = if normal.1 < 0.0 { (r.y - 1, r.y) } else { (r.y + r.h as i32, r.y + r.h as i32 - 1) };
+    let solid = |x, y| tm.tile_at(Vec2i(x, y)).map_or(false, |(t, _)| t.solid && !t.one_way);
+    let face = match (solid(out_x, in_y), solid(in_x, out_y)) {
+        (false, false) => return Some((normal, depth)),
+        (true, false) => Vec2f(0.0, normal.1.signum()),
+        (false, true) => Vec2f(normal.0.signum(), 0.0),
+        // Tucked into an inside corner, where the neighbouring tiles' own contacts do the pushing
+        (true, true) => return None,
+    };
+    let depth = depth_along(shape, tile_box, face);
+    if depth < 0.0 {
+        None
+    } else {
+        Some((face, depth))
+    }
+}
+/// The solid and hazard tiles under r, each as part of the biggest block of identical flat tiles around it
+fn merged_tiles(tm: &Tilemap, map: usize, r: Rect) -> Vec<TileContact> {
+    let TileCoord(x0, y0) = tm.tile_coord(Vec2i(r.x, r.y));
+    let TileCoord(x1, y1) = tm.tile_coord(Vec2i(r.x + r.w as i32 - 1, r.y + r.h as i32 - 1));
+    // (first column, last column, first row, last row, tile)
+    let mut blocks: Vec<(i32, i32, i32, i32, Tile)> = vec![];
+    for ty in y0..=y1 {
+        for tx in x0..=x1 {
+            if blocks.iter().any(|b| (b.0..=b.1).contains(&tx) && (b.2..=b.3).contains(&ty)) {
+                continue;
+            }
+            match tm.tile_at_coord(TileCoord(tx, ty)) {
+                Some((t, _)) if t.solid || t.hazard => blocks.push(block_at(tm, TileCoord(tx, ty), t)),
+                _ => {}
+            }
+        }
+    }
+    blocks
+        .into_iter()
+        .map(|(c0, c1, r0, r1, tile)| {
+            let first = tm.tile_rect(TileCoord(c0, r0));
+            let last = tm.tile_rect(TileCoord(c1, r1));
+            TileContact {
+                tile,
+                rect: Rect {
+                    x: first.x,
+                    y: first.y,
+                    w: (last.x + last.w as i32 - first.x) as u16,
+                    h: (last.y + last.h as i32 - first.y) as u16,
+                },
+                map,
+            }
+        })
+        .collect()
+}
+/// The block of tiles just like tile that c is in: the whole run of them along c's row, stacked with every
+/// row above and below that has exactly the same run. It's the same block whichever of its tiles c is,
+/// so a contact with it stays the same contact as things move along it. Slopes are never merged.
+fn block_at(tm: &Tilemap, TileCoord(x, y): TileCoord, tile: Tile) -> (i32, i32, i32, i32, Tile) {
+    if tile.slope != Slope::None {
+        return (x, x, y, y, tile);
+    }
+    let same = |x, y| tm.tile_at_coord(TileCoord(x, y)).map_or(false, |(t, _)| t == tile);
+    let (mut c0, mut c1) = (x, x);
+    while same(c0 - 1, y) {
+        c0 -= 1;
+    }
+    while same(c1 + 1, y) {
+        c1 += 1;
+    }
+    let same_run = |y| !same(c0 - 1, y) && !same(c1 + 1, y) && (c0..=c1).all(|x| same(x, y));
+    let (mut r0, mut r1) = (y, y);
+    while same_run(r0 - 1) {
+        r0 -= 1;
+    }
+    while same_run(r1 + 1) {
+        r1 += 1;
+    }
+    (c0, c1, r0, r1, tile)
+}
+/// How many pixels into a one-way tile something can sink and still get pushed up on top of it
+pub const ONE_WAY_TOLERANCE: i32 = 4;
 pub fn restitute(positions: &mut Vec<Vec2i>, sizes: &Vec<(usize,usize)>, contacts: &mut Vec<Contact<usize,TileContact>>) {
+    restitute_with(positions, sizes, &|_| Shape::Rect, contacts);
+}
+/// Like `restitute`, for contacts from `gather_contacts_tilemap_shaped`
+pub fn restitute_shaped(positions: &mut [Vec2i], sizes: &[(usize,usize)], colliders: &[Collider], contacts: &mut [Contact<usize,TileContact>]) {
+    restitute_with(positions, sizes, &|i| colliders[i].shape, contacts);
+}
+fn restitute_with(positions: &mut [Vec2i], sizes: &[(usize,usize)], shape_of: &dyn Fn(usize) -> Shape, contacts: &mut [Contact<usize,TileContact>]) {
     // handle restitution of dynamics against dynamics and dynamics against statics wrt contacts.
     // You could instead make contacts `Vec<Contact>` if you think you might remove contacts.
     // You could also add an additional parameter, a slice or vec representing how far we've displaced each dynamic, to avoid allocations if you track a vec of how far things have been moved.
     // You might also want to pass in another &mut Vec<Contact> to be filled in with "real" touches that actually happened.
-    contacts.sort_unstable_by_key(|c| -(c.mtv.0 * c.mtv.0 + c.mtv.1 * c.mtv.1));
+    // Deepest first
+    contacts.sort_unstable_by(|c1, c2| c2.depth.total_cmp(&c1.depth));
     // Keep going!  Note that you can assume every contact has a dynamic object in .a.
     // You might decide to tweak the interface of this function to separately take dynamic-static and dynamic-dynamic contacts, to avoid a branch inside of the response calculation.
-    // Or, you might decide to calculate signed mtvs taking direction into account instead of the unsigned displacements from rect_displacement up above.  Or calculate one MTV per involved entity, then apply displacements to both objects during restitution (sorting by the max or the sum of their magnitudes)
+    // You could also calculate one MTV per involved entity, then apply displacements to both objects during restitution (sorting by the max or the sum of their magnitudes)
     for c in contacts.iter_mut(){
-        let mut a_rect = Rect {
+        if c.trigger {
+            continue;
+        }
+        let a_rect = Rect {
             x: positions[c.a].0,
             y: positions[c.a].1,
             w: sizes[c.a].0 as u16,
             h: sizes[c.a].1 as u16,
         };
-        if let Some((x,y)) = rect_displacement(a_rect, c.b.rect){
-            if x > y {
-                if c.b.rect.y<positions[c.a].1 {
-                    positions[c.a].1 += y
-                } else {
-                    positions[c.a].1 -= y
+        let bottom = a_rect.y + a_rect.h as i32;
+        let tile = c.b.tile;
+        if tile.slope != Slope::None {
+            // Stand on the sloped floor under the middle of the entity; slopes never push sideways.
+            let cx = a_rect.x + a_rect.w as i32 / 2 - c.b.rect.x;
+            if cx < 0 || cx >= c.b.rect.w as i32 {
+                continue;
+            }
+            let floor = c.b.rect.y + c.b.rect.h as i32
+                - tile.slope.floor_height(cx, c.b.rect.w as i32, c.b.rect.h as i32).unwrap_or(0);
+            if bottom > floor {
+                positions[c.a].1 -= bottom - floor;
+            }
+            continue;
+        }
+        if tile.one_way {
+            // Without velocities we can't tell falling from jumping, so only catch things barely into the top.
+            let depth = bottom - c.b.rect.y;
+            if depth > 0 && depth <= ONE_WAY_TOLERANCE {
+                positions[c.a].1 -= depth;
+            }
+            continue;
+        }
+        // Earlier pushes may have moved a already, so work the push out again from where it is now
+        let shape = shape_of(c.a);
+        if shape == Shape::Rect {
+            if let Some((normal, depth)) = rect_mtv(a_rect, c.b.rect) {
+                positions[c.a].0 += normal.0 * depth;
+                positions[c.a].1 += normal.1 * depth;
+            }
+            continue;
+        }
+        // Round shapes keep the normal they were gathered with, so smoothed-over corners stay smooth
+        let placed = shape.place(Vec2f(a_rect.x as f32, a_rect.y as f32), sizes[c.a]);
+        let tile_box = rect_box(c.b.rect);
+        let depth = depth_along(placed, tile_box, c.normal);
+        if depth > 0.0 && overlap(placed, tile_box).is_some() {
+            positions[c.a].0 += (c.normal.0 * depth).round() as i32;
+            positions[c.a].1 += (c.normal.1 * depth).round() as i32;
+        }
+    }
+}
+
+/// How an entity takes part in entity-entity resolution
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mass {
+    /// Moved by overlaps; a heavier entity gets pushed less than a lighter one
+    Dynamic(f32),
+    /// Pushes dynamic entities out of the way but is never moved itself, like a moving platform
+    Kinematic,
+}
+
+/// How many passes `restitute_dynamic` makes at most; more lets longer chains settle
+pub const DYNAMIC_ITERATIONS: usize = 8;
+
+/// Push overlapping entities apart, splitting each push between the two sides by mass.
+/// An entity that's touching a kinematic one, directly or through a chain of other entities, holds firm
+/// against entities further along the chain, so a stack of crates on a kinematic floor settles in one pass.
+/// Only the pairs in `contacts` are resolved: each pass re-measures their overlaps from where things are now,
+/// for up to `DYNAMIC_ITERATIONS` passes or until none of them overlap. A push that shoves an entity into one
+/// it wasn't already touching is left for the next tick's contacts to catch.
+/// Trigger contacts and contacts between two kinematic entities are ignored.
+pub fn restitute_dynamic(positions: &mut [Vec2i], sizes: &[(usize,usize)], masses: &[Mass], colliders: &[Collider], contacts: &[Contact<usize,usize>]) {
+    // How many contacts away from a kinematic entity each entity is
+    let mut rank = vec![usize::MAX; positions.len()];
+    let mut frontier: Vec<usize> = (0..positions.len()).filter(|&i| masses[i] == Mass::Kinematic).collect();
+    for &i in frontier.iter() {
+        rank[i] = 0;
+    }
+    let mut r = 0;
+    while !frontier.is_empty() {
+        r += 1;
+        let mut next = vec![];
+        for c in contacts.iter().filter(|c| !c.trigger) {
+            for (from, to) in [(c.a, c.b), (c.b, c.a)] {
+                if rank[from] == r - 1 && rank[to] == usize::MAX {
+                    rank[to] = r;
+                    next.push(to);
                 }
             }
-            else {
-                if c.b.rect.x<positions[c.a].0   {
-                    positions[c.a].0 += x
-                    } else {
-                        positions[c.a].0 -= x                   
+        }
+        frontier = next;
+    }
+    // Settle things nearest the kinematic entities first
+    let mut order: Vec<&Contact<usize,usize>> = contacts.iter().collect();
+    order.sort_by_key(|c| rank[c.a].min(rank[c.b]));
+    // Work in fractional pixels, or a one pixel overlap split in half could never get resolved
+    let mut at: Vec<Vec2f> = positions.iter().map(|p| Vec2f(p.0 as f32, p.1 as f32)).collect();
+    for _ in 0..DYNAMIC_ITERATIONS {
+        let mut deepest = 0.0_f32;
+        for c in order.iter() {
+            // How much of the push each side takes
+            if c.trigger {
+                continue;
+            }
+            let share_a = match (masses[c.a], masses[c.b]) {
+                (Mass::Kinematic, Mass::Kinematic) => continue,
+                (Mass::Kinematic, _) => 0.0,
+                (_, Mass::Kinematic) => 1.0,
+                _ if rank[c.a] < rank[c.b] => 0.0,
+                _ if rank[c.b] < rank[c.a] => 1.0,
+                (Mass::Dynamic(ma), Mass::Dynamic(mb)) if ma + mb > 0.0 => mb / (ma + mb),
+                _ => 0.5,
+            };
+            let (normal, depth) = match shape_overlap(colliders[c.a].shape, at[c.a], sizes[c.a], colliders[c.b].shape, at[c.b], sizes[c.b]) {
+                Some(o) if o.1 > 0.0 => o,
+                _ => continue,
+            };
+            at[c.a].0 += normal.0 * depth * share_a;
+            at[c.a].1 += normal.1 * depth * share_a;
+            at[c.b].0 -= normal.0 * depth * (1.0 - share_a);
+            at[c.b].1 -= normal.1 * depth * (1.0 - share_a);
+            deepest = deepest.max(depth);
+        }
+        if deepest < 0.01 {
+            break;
+        }
+    }
+    // Round every position the same way so touching entities stay touching
+    for (p, v) in positions.iter_mut().zip(at) {
+        *p = v.to_vec2i();
+    }
+}
+/// When and where a moving rect first touches something during one step of movement
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SweepHit {
+    /// Time of impact: how far through the step the touch happens, from 0.0 to 1.0
+    pub toi: f32,
+    /// Which face of the thing was hit, pointing back at the mover, e.g. (0, -1) when landing on top of it
+    pub normal: (i32, i32),
+    /// The rect that was hit
+    pub rect: Rect,
+}
+
+/// When `moving`, travelling by `vel` this step, first touches `target`.
+/// Rects which already overlap, or which only graze corners, don't count.
+pub fn sweep_rect(moving: Rect, vel: Vec2i, target: Rect) -> Option<SweepHit> {
+    // The times the mover's span on one axis starts and stops overlapping the target's
+    let axis = |m0: i32, mlen: u16, t0: i32, tlen: u16, v: i32| -> Option<(f32, f32)> {
+        let (m1, t1) = (m0 + mlen as i32, t0 + tlen as i32);
+        if v == 0 {
+            // Not moving on this axis, so it overlaps for the whole step or never
+            return if m0 < t1 && t0 < m1 {
+                Some((f32::NEG_INFINITY, f32::INFINITY))
+            } else {
+                None
+            };
+        }
+        let v = v as f32;
+        if v > 0.0 {
+            Some(((t0 - m1) as f32 / v, (t1 - m0) as f32 / v))
+        } else {
+            Some(((t1 - m0) as f32 / v, (t0 - m1) as f32 / v))
+        }
+    };
+    let (x_entry, x_exit) = axis(moving.x, moving.w, target.x, target.w, vel.0)?;
+    let (y_entry, y_exit) = axis(moving.y, moving.h, target.y, target.h, vel.1)?;
+    let entry = x_entry.max(y_entry);
+    let exit = x_exit.min(y_exit);
+    if entry >= exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+    let normal = if x_entry > y_entry {
+        (-vel.0.signum(), 0)
+    } else {
+        (0, -vel.1.signum())
+    };
+    Some(SweepHit {
+        toi: entry,
+        normal,
+        rect: target,
+    })
+}
+
+/// The first solid tile `moving` runs into while travelling by `vel` this step.
+/// One-way tiles only stop things coming down onto them; sloped tiles are left to `restitute`.
+pub fn sweep_tilemap(moving: Rect, vel: Vec2i, map: &Tilemap) -> Option<(SweepHit, TileContact)> {
+    // Every tile the rect could touch on its way
+    let x0 = moving.x.min(moving.x + vel.0);
+    let y0 = moving.y.min(moving.y + vel.1);
+    let x1 = moving.x.max(moving.x + vel.0) + moving.w as i32;
+    let y1 = moving.y.max(moving.y + vel.1) + moving.h as i32;
+    let TileCoord(tx0, ty0) = map.tile_coord(Vec2i(x0, y0));
+    let TileCoord(tx1, ty1) = map.tile_coord(Vec2i(x1, y1));
+    let mut best: Option<(SweepHit, TileContact)> = None;
+    for ty in ty0..=ty1 {
+        for tx in tx0..=tx1 {
+            let (tile, rect) = match map.tile_at_coord(TileCoord(tx, ty)) {
+                Some(t) if t.0.solid && t.0.slope == Slope::None => t,
+                _ => continue,
+            };
+            if let Some(hit) = sweep_rect(moving, vel, rect) {
+                if tile.one_way && hit.normal != (0, -1) {
+                    continue;
+                }
+                if best.map_or(true, |(b, _)| hit.toi < b.toi) {
+                    best = Some((hit, TileContact { tile, rect, map: 0 }));
                 }
             }
         }
     }
+    best
+}
+
+/// Move an entity by `vel`, stopping where it first touches a solid tile and then sliding along that surface
+/// with whatever movement is left. Returns what it hit along the way, e.g. to check for landing on the ground.
+pub fn move_and_slide(position: &mut Vec2i, size: (usize, usize), vel: Vec2i, map: &Tilemap) -> Vec<(SweepHit, TileContact)> {
+    let mut hits = vec![];
+    let mut remaining = vel;
+    // Each slide removes one axis of movement, so two hits and a final move is all it takes
+    for _ in 0..3 {
+        if remaining == Vec2i(0, 0) {
+            break;
+        }
+        let rect = Rect {
+            x: position.0,
+            y: position.1,
+            w: size.0 as u16,
+            h: size.1 as u16,
+        };
+        let (hit, contact) = match sweep_tilemap(rect, remaining, map) {
+            Some(h) => h,
+            None => {
+                position.0 += remaining.0;
+                position.1 += remaining.1;
+                break;
+            }
+        };
+        // Go exactly up to the face we hit, and as far along the other axis as the same time allows
+        let along = |v: i32| (v as f32 * hit.toi) as i32;
+        let travel = match hit.normal {
+            (-1, 0) => Vec2i(hit.rect.x - (rect.x + rect.w as i32), along(remaining.1)),
+            (1, 0) => Vec2i(hit.rect.x + hit.rect.w as i32 - rect.x, along(remaining.1)),
+            (0, -1) => Vec2i(along(remaining.0), hit.rect.y - (rect.y + rect.h as i32)),
+            _ => Vec2i(along(remaining.0), hit.rect.y + hit.rect.h as i32 - rect.y),
+        };
+        position.0 += travel.0;
+        position.1 += travel.1;
+        remaining = Vec2i(remaining.0 - travel.0, remaining.1 - travel.1);
+        // Slide: drop the part of the movement going into the surface
+        if hit.normal.0 != 0 {
+            remaining.0 = 0;
+        } else {
+            remaining.1 = 0;
+        }
+        hits.push((hit, contact));
+    }
+    hits
 }
@@ -0,0 +1,340 @@
+//! Ready-made character controllers that move a `Body` through a level's tilemaps.
+//!
+//! Controllers steer their body's velocity and then `Body::step` it, so its gravity scale, drag, friction and
+//! speed limit all still apply. Then they move it a pixel at a time against the tiles to where the step put it,
+//! so they never tunnel and always end up flush against whatever they hit. Speeds are in pixels per second, like `Body`.
+use crate::physics::{Body, STEP};
+use crate::sprite::Direction;
+use crate::tiles::*;
+use crate::types::*;
+use winit::event::VirtualKeyCode;
+use winit_input_helper::WinitInputHelper;
+
+/// What the player is asking a `PlatformerController` to do this tick
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PlatformerInput {
+    pub left: bool,
+    pub right: bool,
+    /// Jump was pressed this tick
+    pub jump_pressed: bool,
+    /// Jump is being held; letting go early makes a shorter jump
+    pub jump_held: bool,
+    /// Down was pressed this tick, to drop through a one-way platform
+    pub down_pressed: bool,
+}
+impl PlatformerInput {
+    /// The arrow keys to move and drop, and space or up to jump
+    pub fn from_keys(input: &WinitInputHelper) -> Self {
+        Self {
+            left: input.key_held(VirtualKeyCode::Left),
+            right: input.key_held(VirtualKeyCode::Right),
+            jump_pressed: input.key_pressed(VirtualKeyCode::Space) || input.key_pressed(VirtualKeyCode::Up),
+            jump_held: input.key_held(VirtualKeyCode::Space) || input.key_held(VirtualKeyCode::Up),
+            down_pressed: input.key_pressed(VirtualKeyCode::Down),
+        }
+    }
+}
+
+/// Side-view running and jumping on solid tiles, slopes and one-way platforms
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlatformerController {
+    pub run_speed: f32,
+    /// How quickly the character gets up to speed or turns around, on the ground and in the air
+    pub ground_accel: f32,
+    pub air_accel: f32,
+    /// How hard the character falls, scaled by its body's `gravity_scale`
+    pub gravity: f32,
+    pub max_fall: f32,
+    /// How fast a jump starts out going up
+    pub jump_speed: f32,
+    /// What's left of the upward speed when jump is let go early
+    pub jump_cut: f32,
+    /// How long after running off a ledge a jump still works, in seconds
+    pub coyote_time: f32,
+    /// How long before landing a jump press still counts, in seconds
+    pub jump_buffer: f32,
+    /// How long one-way platforms are ignored after dropping through one, in seconds
+    pub drop_time: f32,
+    /// The most the character steps up or down per pixel of running, which lets it walk along slopes
+    pub step_height: i32,
+    /// Standing on something this tick
+    pub grounded: bool,
+    /// Hit its head on a ceiling this tick
+    pub bonked: bool,
+    since_grounded: f32,
+    since_jump_pressed: f32,
+    drop_timer: f32,
+    // Still rising from a jump, so letting go of jump can cut it short
+    jumping: bool,
+}
+impl PlatformerController {
+    /// A controller tuned for a character about one 16 pixel tile wide
+    pub fn new() -> Self {
+        Self {
+            run_speed: 90.0,
+            ground_accel: 900.0,
+            air_accel: 500.0,
+            gravity: 900.0,
+            max_fall: 360.0,
+            jump_speed: 300.0,
+            jump_cut: 0.5,
+            coyote_time: 0.1,
+            jump_buffer: 0.1,
+            drop_time: 0.25,
+            step_height: 2,
+            grounded: false,
+            bonked: false,
+            since_grounded: f32::INFINITY,
+            since_jump_pressed: f32::INFINITY,
+            drop_timer: 0.0,
+            jumping: false,
+        }
+    }
+    /// Run one `STEP` of movement for a body with a size-sized box. Every solid tile in maps blocks it.
+    pub fn update(&mut self, body: &mut Body, size: (usize, usize), input: &PlatformerInput, maps: &[Tilemap]) {
+        let dt = STEP;
+        self.since_jump_pressed = if input.jump_pressed { 0.0 } else { self.since_jump_pressed + dt };
+        self.drop_timer -= dt;
+        let rect = |p: Vec2i| Rect::new(p.0, p.1, size.0 as u16, size.1 as u16);
+        let from = body.pixel();
+        self.grounded = self.on_ground(maps, rect(from));
+        if self.grounded {
+            self.since_grounded = 0.0;
+            // Standing on a one-way platform and nothing else: drop through it
+            if input.down_pressed && !blocked(maps, rect(from + Vec2i(0, 1)), false) {
+                self.drop_timer = self.drop_time;
+                self.grounded = false;
+            }
+        } else {
+            self.since_grounded += dt;
+        }
+
+        // Run, easing towards the speed the input asks for
+        let want = (input.right as i32 - input.left as i32) as f32 * self.run_speed;
+        let accel = if self.grounded { self.ground_accel } else { self.air_accel } * dt;
+        body.velocity.0 += (want - body.velocity.0).clamp(-accel, accel);
+
+        // Jump if jump was pressed recently and we were on the ground recently
+        if self.since_jump_pressed <= self.jump_buffer && self.since_grounded <= self.coyote_time {
+            body.velocity.1 = -self.jump_speed;
+            self.jumping = true;
+            self.grounded = false;
+            self.since_jump_pressed = f32::INFINITY;
+            self.since_grounded = f32::INFINITY;
+        }
+        if self.jumping && (body.velocity.1 >= 0.0 || !input.jump_held) {
+            if body.velocity.1 < 0.0 {
+                body.velocity.1 *= self.jump_cut;
+            }
+            self.jumping = false;
+        }
+        // Fall, leaving room for this step's gravity under max_fall
+        let gravity = if self.grounded { 0.0 } else { self.gravity };
+        if !self.grounded {
+            body.velocity.1 = body.velocity.1.min(self.max_fall - gravity * body.gravity_scale * dt);
+        } else if body.velocity.1 > 0.0 {
+            body.velocity.1 = 0.0;
+        }
+        body.step(Vec2f(0.0, gravity), dt);
+
+        // Move across, stepping up and down slopes while on the ground
+        let target = body.position;
+        let mut at = from;
+        let dx = target.to_vec2i().0 - at.0;
+        let mut stopped_x = false;
+        for _ in 0..dx.abs() {
+            let next = at + Vec2i(dx.signum(), 0);
+            let step_up = (0..=self.step_height).find(|&up| !self.blocks(maps, rect(next - Vec2i(0, up)), false));
+            match step_up {
+                Some(up) if up == 0 || self.grounded => at = next - Vec2i(0, up),
+                _ => {
+                    stopped_x = true;
+                    break;
+                }
+            }
+            // Follow the ground down a slope rather than running off into the air
+            if self.grounded && !self.on_ground(maps, rect(at)) {
+                if let Some(down) = (1..=self.step_height).find(|&down| self.on_ground(maps, rect(at + Vec2i(0, down)))) {
+                    at.1 += down;
+                }
+            }
+        }
+        // Then up or down
+        let dy = target.to_vec2i().1 - from.1;
+        let mut stopped_y = false;
+        self.bonked = false;
+        for _ in 0..dy.abs() {
+            let next = at + Vec2i(0, dy.signum());
+            // Only landing on a one-way platform counts; jumping up through one doesn't
+            if self.blocks(maps, rect(next), dy > 0) {
+                stopped_y = true;
+                self.bonked = dy < 0;
+                break;
+            }
+            at = next;
+        }
+        // Keep the sub-pixel part of any movement that wasn't stopped, plus any steps up or down slopes
+        if stopped_x {
+            body.position.0 = at.0 as f32;
+            body.velocity.0 = 0.0;
+        } else {
+            body.position.0 = target.0;
+        }
+        if stopped_y {
+            body.position.1 = at.1 as f32;
+            body.velocity.1 = 0.0;
+            self.jumping = false;
+        } else {
+            body.position.1 = target.1 + (at.1 - target.to_vec2i().1) as f32;
+        }
+        self.grounded = self.on_ground(maps, rect(body.pixel()));
+    }
+    /// Whether something under r would hold it up
+    fn on_ground(&self, maps: &[Tilemap], r: Rect) -> bool {
+        self.blocks(maps, Rect { y: r.y + 1, ..r }, true)
+    }
+    fn blocks(&self, maps: &[Tilemap], r: Rect, one_ways: bool) -> bool {
+        blocked(maps, r, one_ways && self.drop_timer <= 0.0)
+    }
+}
+impl Default for PlatformerController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which way the arrow keys point: -1.0, 0.0 or 1.0 on each axis
+pub fn arrow_axis(input: &WinitInputHelper) -> Vec2f {
+    let axis = |neg, pos| (input.key_held(pos) as i32 - input.key_held(neg) as i32) as f32;
+    Vec2f(
+        axis(VirtualKeyCode::Left, VirtualKeyCode::Right),
+        axis(VirtualKeyCode::Up, VirtualKeyCode::Down),
+    )
+}
+
+/// Top-down walking in eight directions, sliding along solid tiles
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TopDownController {
+    /// The top speed in any direction, diagonals included
+    pub speed: f32,
+    /// How quickly the character gets up to speed or changes direction
+    pub accel: f32,
+    /// How quickly the character stops once there's no input
+    pub decel: f32,
+    /// Somewhere the character's box has to stay inside, like the edges of the level
+    pub bounds: Option<Rect>,
+    /// Which way the character last moved; it keeps facing that way while standing still
+    pub facing: Direction,
+}
+impl TopDownController {
+    /// A controller with a top speed of speed that gets there in a tenth of a second
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            accel: speed * 10.0,
+            decel: speed * 10.0,
+            bounds: None,
+            facing: Direction::Down,
+        }
+    }
+    /// Run one `STEP` of movement for a body with a size-sized box, steering towards axis (each part
+    /// from -1.0 to 1.0, e.g. from `arrow_axis`). Every solid tile in maps blocks it; one-way tiles don't.
+    pub fn update(&mut self, body: &mut Body, size: (usize, usize), axis: Vec2f, maps: &[Tilemap]) {
+        let dt = STEP;
+        // Going diagonally is no faster than going straight
+        let dir = if axis.length() > 1.0 { axis.normalized() } else { axis };
+        let want = dir * self.speed;
+        let rate = if want == Vec2f(0.0, 0.0) { self.decel } else { self.accel } * dt;
+        let dv = want - body.velocity;
+        body.velocity += if dv.length() > rate { dv.normalized() * rate } else { dv };
+        // Up and down win ties, so walking diagonally faces the way the sprite shows best
+        if axis.1 != 0.0 && axis.1.abs() >= axis.0.abs() {
+            self.facing = if axis.1 < 0.0 { Direction::Up } else { Direction::Down };
+        } else if axis.0 != 0.0 {
+            self.facing = if axis.0 < 0.0 { Direction::Left } else { Direction::Right };
+        }
+
+        // Only steps out past an edge of bounds are stopped, so something outside them (say after a
+        // teleport) can still walk back in
+        let past_bounds = |r: Rect, step: Vec2i| {
+            self.bounds.map_or(false, |b| {
+                (step.0 < 0 && r.x < b.x)
+                    || (step.0 > 0 && r.x + r.w as i32 > b.x + b.w as i32)
+                    || (step.1 < 0 && r.y < b.y)
+                    || (step.1 > 0 && r.y + r.h as i32 > b.y + b.h as i32)
+            })
+        };
+        let rect = |p: Vec2i| Rect::new(p.0, p.1, size.0 as u16, size.1 as u16);
+        let free = |r: Rect, step: Vec2i| !past_bounds(r, step) && !blocked(maps, r, false);
+
+        // Nothing pulls down in a top-down view, but drag, friction and the speed limit still apply
+        let mut at = body.pixel();
+        body.step(Vec2f(0.0, 0.0), dt);
+        // Move one axis at a time, so running into a wall at an angle slides along it
+        let target = body.position;
+        let d = target.to_vec2i() - at;
+        let mut stopped = (false, false);
+        for _ in 0..d.0.abs() {
+            let step = Vec2i(d.0.signum(), 0);
+            if !free(rect(at + step), step) {
+                stopped.0 = true;
+                break;
+            }
+            at = at + step;
+        }
+        for _ in 0..d.1.abs() {
+            let step = Vec2i(0, d.1.signum());
+            if !free(rect(at + step), step) {
+                stopped.1 = true;
+                break;
+            }
+            at = at + step;
+        }
+        // Keep the sub-pixel part of any movement that wasn't stopped
+        if stopped.0 {
+            body.position.0 = at.0 as f32;
+            body.velocity.0 = 0.0;
+        } else {
+            body.position.0 = target.0;
+        }
+        if stopped.1 {
+            body.position.1 = at.1 as f32;
+            body.velocity.1 = 0.0;
+        } else {
+            body.position.1 = target.1;
+        }
+    }
+}
+
+/// Whether r overlaps a solid tile of any of maps, or the solid part of a slope. One-way tiles only count
+/// (if at all) when r's bottom row is their top row, i.e. when r has just come down onto them from above.
+fn blocked(maps: &[Tilemap], r: Rect, one_ways: bool) -> bool {
+    r.w != 0 && r.h != 0 && maps.iter().any(|map| blocked_by(map, r, one_ways))
+}
+fn blocked_by(map: &Tilemap, r: Rect, one_ways: bool) -> bool {
+    let (right, bottom) = (r.x + r.w as i32, r.y + r.h as i32);
+    let TileCoord(x0, y0) = map.tile_coord(Vec2i(r.x, r.y));
+    let TileCoord(x1, y1) = map.tile_coord(Vec2i(right - 1, bottom - 1));
+    for ty in y0..=y1 {
+        for tx in x0..=x1 {
+            let (tile, t) = match map.tile_at_coord(TileCoord(tx, ty)) {
+                Some((tile, t)) if tile.solid => (tile, t),
+                _ => continue,
+            };
+            let hit = if tile.one_way {
+                one_ways && bottom - 1 == t.y
+            } else if tile.slope != Slope::None {
+                // The slope's highest point under r; slopes only rise one way, so it's at one of r's edges
+                let (ox0, ox1) = (r.x.max(t.x) - t.x, right.min(t.x + t.w as i32) - t.x);
+                let floor = |x| tile.slope.floor_height(x, t.w as i32, t.h as i32).unwrap_or(0);
+                bottom > t.y + t.h as i32 - floor(ox0).max(floor(ox1))
+            } else {
+                true
+            };
+            if hit {
+                return true;
+            }
+        }
+    }
+    false
+}
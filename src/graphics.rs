@@ -130,7 +130,14 @@ impl<'fb> Screen<'fb> {
         }
     }
     // Bitblt too begins with a translation
-    pub fn bitblt(&mut self, src: &Texture, from: Rect, Vec2i(to_x, to_y): Vec2i) {
+    pub fn bitblt(&mut self, src: &Texture, from: Rect, to: Vec2i) {
+        self.bitblt_alpha(src, from, to, 255);
+    }
+    // Same as bitblt, but the source is faded by alpha (255 is opaque, 0 draws nothing)
+    pub fn bitblt_alpha(&mut self, src: &Texture, from: Rect, Vec2i(to_x, to_y): Vec2i, alpha: u8) {
+        if alpha == 0 {
+            return;
+        }
         let (tw, th) = src.size();
         // println!("tw is:{}",tw);
         // println!("from.x is:{}",from.x);
@@ -181,11 +188,14 @@ impl<'fb> Screen<'fb> {
                 [(depth * (from.x + x_skip) as usize)..(depth * (from.x + x_count) as usize)]
                 .chunks_exact(depth);
             // Composite over, assume premultiplied rgba8888
+            // Premultiplied alpha means fading the source scales every channel.
+            let k = alpha as f32 / 255.0;
             for (to, from) in to_cols.zip(from_cols) {
                 let ta = to[3] as f32 / 255.0;
-                let fa = from[3] as f32 / 255.0;
+                let fa = from[3] as f32 / 255.0 * k;
                 for i in 0..3 {
-                    to[i] = from[i].saturating_add((to[i] as f32 * (1.0 - fa)).round() as u8);
+                    let fc = (from[i] as f32 * k).round() as u8;
+                    to[i] = fc.saturating_add((to[i] as f32 * (1.0 - fa)).round() as u8);
                 }
                 to[3] = ((fa + ta * (1.0 - fa)) * 255.0).round() as u8;
             }
@@ -0,0 +1,157 @@
+use crate::graphics::Screen;
+use crate::tiles::*;
+use crate::types::*;
+
+use std::ops::RangeBounds;
+use std::rc::Rc;
+
+/// One grid of a LayeredTilemap, plus how it's drawn and whether it collides.
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    /// 0.0 is invisible, 1.0 is fully opaque
+    pub opacity: f32,
+    /// How far the layer moves per pixel of camera movement in x and y:
+    /// (1.0, 1.0) moves with the world, smaller is further away, and (0.0, 0.0) is stuck to the screen.
+    pub parallax: (f32, f32),
+    /// Layers are drawn in increasing z order (ties are drawn in the order they were added)
+    pub z: i32,
+    /// Whether solid tiles on this layer should produce contacts
+    pub collides: bool,
+    tilemap: Tilemap,
+}
+impl TileLayer {
+    pub fn tilemap(&self) -> &Tilemap {
+        &self.tilemap
+    }
+    // Edits go through the layer rather than a `&mut Tilemap`, so a layer can't be moved away from the others
+
+    /// Replace the tile at tile coordinates (x, y), as in `Tilemap::set_tile`
+    pub fn set_tile(&mut self, x: usize, y: usize, id: TileID) -> Result<TileID, TileError> {
+        self.tilemap.set_tile(x, y, id)
+    }
+    pub fn fill(&mut self, region: TileRect, id: TileID) -> Result<(), TileError> {
+        self.tilemap.fill(region, id)
+    }
+    pub fn copy_region(&mut self, from: TileRect, to: (usize, usize)) -> Result<(), TileError> {
+        self.tilemap.copy_region(from, to)
+    }
+    pub fn paint_terrain(&mut self, x: usize, y: usize, name: &str) -> Result<(), TileError> {
+        self.tilemap.paint_terrain(x, y, name)
+    }
+    pub fn erase_terrain(&mut self, x: usize, y: usize, id: TileID) -> Result<(), TileError> {
+        self.tilemap.erase_terrain(x, y, id)
+    }
+    /// The regions changed since the last call, as in `Tilemap::take_dirty`
+    pub fn take_dirty(&mut self) -> Vec<TileRect> {
+        self.tilemap.take_dirty()
+    }
+}
+
+/// A stack of tilemaps sharing one position and size, e.g. ground, walls, and treetops.
+pub struct LayeredTilemap {
+    position: Vec2i,
+    dims: (usize, usize),
+    layers: Vec<TileLayer>,
+}
+impl LayeredTilemap {
+    pub fn new(position: Vec2i, dims: (usize, usize)) -> Self {
+        Self {
+            position,
+            dims,
+            layers: vec![],
+        }
+    }
+    /// Add a layer on top of the existing ones, visible and colliding by default.
+    pub fn add_layer(
+        &mut self,
+        name: &str,
+        tileset: &Rc<Tileset>,
+        map: Vec<usize>,
+    ) -> &mut TileLayer {
+        let tilemap = Tilemap::new(self.position, self.dims, tileset, map);
+        self.add_tilemap(name, tilemap)
+    }
+    /// Add an existing tilemap as the top layer; it's moved to this map's position.
+    pub fn add_tilemap(&mut self, name: &str, mut tilemap: Tilemap) -> &mut TileLayer {
+        assert_eq!(tilemap.size(), self.dims, "Layer is the wrong size!");
+        if let Some(first) = self.layers.first() {
+            assert_eq!(
+                tilemap.tile_size(),
+                first.tilemap.tile_size(),
+                "Layers must all use the same tile size"
+            );
+        }
+        tilemap.position = self.position;
+        let z = self.layers.iter().map(|l| l.z + 1).max().unwrap_or(0);
+        self.layers.push(TileLayer {
+            name: name.to_string(),
+            visible: true,
+            opacity: 1.0,
+            parallax: (1.0, 1.0),
+            z,
+            collides: true,
+            tilemap,
+        });
+        self.layers.last_mut().unwrap()
+    }
+    pub fn position(&self) -> Vec2i {
+        self.position
+    }
+    pub fn set_position(&mut self, position: Vec2i) {
+        self.position = position;
+        for l in self.layers.iter_mut() {
+            l.tilemap.position = position;
+        }
+    }
+    pub fn size(&self) -> (usize, usize) {
+        self.dims
+    }
+    pub fn layers(&self) -> &[TileLayer] {
+        &self.layers
+    }
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find(|l| l.name == name)
+    }
+    /// The tilemaps of layers which take part in collision
+    pub fn colliding(&self) -> impl Iterator<Item = &Tilemap> {
+        self.layers
+            .iter()
+            .filter(|l| l.collides)
+            .map(|l| &l.tilemap)
+    }
+    /// The first solid tile at posn on any colliding layer
+    pub fn solid_at(&self, posn: Vec2i) -> Option<(Tile, Rect)> {
+        self.colliding()
+            .filter_map(|tm| tm.tile_at(posn))
+            .find(|(t, _r)| t.solid)
+    }
+    /// Draw every visible layer, back to front; `tick` animates tiles as in `Tilemap::draw`.
+    pub fn draw(&self, screen: &mut Screen, tick: usize) {
+        self.draw_layers(screen, tick, ..);
+    }
+    /// Draw the visible layers whose z is in `zs`, back to front.
+    /// Drawing `..0`, then sprites, then `0..` puts sprites between background and foreground layers.
+    pub fn draw_layers(&self, screen: &mut Screen, tick: usize, zs: impl RangeBounds<i32>) {
+        let mut order: Vec<&TileLayer> = self
+            .layers
+            .iter()
+            .filter(|l| l.visible && zs.contains(&l.z))
+            .collect();
+        order.sort_by_key(|l| l.z);
+        let camera = screen.bounds();
+        for l in order {
+            // A parallax layer is drawn as if the camera were only partway along
+            screen.set_scroll(Vec2i(
+                (camera.x as f32 * l.parallax.0).round() as i32,
+                (camera.y as f32 * l.parallax.1).round() as i32,
+            ));
+            let alpha = (l.opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+            l.tilemap.draw_alpha(screen, tick, alpha);
+        }
+        screen.set_scroll(Vec2i(camera.x, camera.y));
+    }
+}
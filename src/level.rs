@@ -0,0 +1,305 @@
+//! Spawn points, and the engine's own level file format.
+//!
+//! A level file is JSON holding a format version, the tilesets, the tilemaps, and the spawn points
+//! of one level, so levels can live under `content/` instead of in code. For example:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "tilesets": [{
+//!     "image": "jack/collage.png",
+//!     "grid": { "tile_w": 16, "tile_h": 16, "margin": 0, "spacing": 0 },
+//!     "tiles": [{}, { "solid": true }, { "hazard": true, "damage": 1 }],
+//!     "animations": { "2": [[2, 10], [3, 10]] },
+//!     "tags": { "1": { "sound": "stone" } },
+//!     "terrains": {}
+//!   }],
+//!   "tilemaps": [{
+//!     "position": [0, 0],
+//!     "dims": [3, 2],
+//!     "tileset": 0,
+//!     "tiles": [1, 1, 1, 0, null, 2]
+//!   }],
+//!   "spawns": [{ "kind": "Player", "position": [16, 0] }]
+//! }
+//! ```
+//!
+//! - `image` is relative to the level file.
+//! - `grid` defaults to 16x16 tiles with no margin or spacing.
+//! - Each tile lists only the `Tile` fields that differ from `Tile::OPEN`.
+//! - `animations` maps a tile index to its (tile index, ticks) frames, as in `Tileset::add_animation`.
+//! - `tags` maps a tile index to named ints or strings, and `terrains` holds `Terrain` autotiling rules.
+//! - `animations`, `tags`, and `terrains` may be left out.
+//! - A tilemap's `tileset` is an index into `tilesets`, and its `tiles` are row-major tile indices with `null` for empty cells.
+//! - A spawn's `kind` is whatever the game's entity type serializes to; `size` and `name` may be left out.
+use crate::texture::Texture;
+use crate::tiles::*;
+use crate::types::Vec2i;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Where an entity of some kind should be created when a level starts.
+/// `K` is the game's own entity type (each game defines its own `EntityType`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Spawn<K> {
+    pub kind: K,
+    /// Top-left corner, in world pixels
+    pub position: Vec2i,
+    /// Width and height in pixels, or (0,0) if the spawn point is just a point
+    #[serde(default)]
+    pub size: (usize, usize),
+    /// An optional name, e.g. the object name given in an editor
+    #[serde(default)]
+    pub name: String,
+}
+
+impl<K> Spawn<K> {
+    pub fn new(kind: K, position: Vec2i) -> Self {
+        Self {
+            kind,
+            position,
+            size: (0, 0),
+            name: String::new(),
+        }
+    }
+    /// Convert the kind of this spawn point, e.g. from the string an editor gave us to a game's entity type.
+    pub fn map_kind<L>(self, f: impl FnOnce(K) -> L) -> Spawn<L> {
+        Spawn {
+            kind: f(self.kind),
+            position: self.position,
+            size: self.size,
+            name: self.name,
+        }
+    }
+}
+
+/// The format version `LevelFile::new` writes; older files are still read, newer ones are refused.
+pub const LEVEL_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(PathBuf, std::io::Error),
+    Image(PathBuf, image::ImageError),
+    Parse(PathBuf, serde_json::Error),
+    /// The file was written by a newer version of the format
+    Version(u32),
+    /// The file parsed, but refers to things that don't exist
+    Invalid(String),
+}
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io(p, e) => write!(f, "couldn't access {}: {}", p.display(), e),
+            LevelError::Image(p, e) => write!(f, "couldn't load image {}: {}", p.display(), e),
+            LevelError::Parse(p, e) => write!(f, "couldn't parse {}: {}", p.display(), e),
+            LevelError::Version(v) => write!(
+                f,
+                "level format version {} is newer than {}",
+                v, LEVEL_FORMAT_VERSION
+            ),
+            LevelError::Invalid(what) => write!(f, "invalid level: {}", what),
+        }
+    }
+}
+impl std::error::Error for LevelError {}
+
+/// A level as stored on disk; see the module docs for the format.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LevelFile<K> {
+    pub version: u32,
+    pub tilesets: Vec<TilesetData>,
+    pub tilemaps: Vec<TilemapData>,
+    #[serde(default = "Vec::new")]
+    pub spawns: Vec<Spawn<K>>,
+}
+/// Everything about a `Tileset` except its texture, which is named by path instead
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TilesetData {
+    pub image: String,
+    #[serde(default)]
+    pub grid: TileGrid,
+    pub tiles: Vec<Tile>,
+    #[serde(default)]
+    pub animations: BTreeMap<usize, Vec<(usize, usize)>>,
+    #[serde(default)]
+    pub tags: BTreeMap<usize, BTreeMap<String, TileTag>>,
+    #[serde(default)]
+    pub terrains: BTreeMap<String, Terrain>,
+}
+/// A `Tilemap`, with its tileset given as an index into the level's tilesets
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TilemapData {
+    pub position: Vec2i,
+    pub dims: (usize, usize),
+    pub tileset: usize,
+    pub tiles: Vec<Option<usize>>,
+}
+/// What a `LevelFile` builds into
+pub struct Level<K> {
+    pub tilesets: Vec<Rc<Tileset>>,
+    pub tilemaps: Vec<Tilemap>,
+    pub spawns: Vec<Spawn<K>>,
+}
+
+impl<K> LevelFile<K> {
+    pub fn new() -> Self {
+        Self {
+            version: LEVEL_FORMAT_VERSION,
+            tilesets: vec![],
+            tilemaps: vec![],
+            spawns: vec![],
+        }
+    }
+    /// Record a tileset whose texture was loaded from `image` (relative to where the level will be saved).
+    /// Returns the index to pass to `add_tilemap`.
+    pub fn add_tileset(&mut self, image: &str, tileset: &Tileset) -> usize {
+        self.tilesets.push(TilesetData {
+            image: image.to_string(),
+            grid: tileset.grid(),
+            tiles: tileset.tiles.clone(),
+            animations: tileset
+                .animations()
+                .iter()
+                .map(|(id, a)| (*id, a.frames.iter().map(|(f, d)| (f.index(), *d)).collect()))
+                .collect(),
+            tags: tileset
+                .tags()
+                .iter()
+                .map(|(id, tags)| (*id, tags.clone().into_iter().collect()))
+                .collect(),
+            terrains: tileset.terrains().clone().into_iter().collect(),
+        });
+        self.tilesets.len() - 1
+    }
+    /// Record a tilemap drawn with the tileset at index `tileset`
+    pub fn add_tilemap(&mut self, tilemap: &Tilemap, tileset: usize) {
+        assert!(tileset < self.tilesets.len(), "No such tileset");
+        self.tilemaps.push(TilemapData {
+            position: tilemap.position,
+            dims: tilemap.size(),
+            tileset,
+            tiles: tilemap
+                .ids()
+                .iter()
+                .map(|id| if id.is_empty() { None } else { Some(id.index()) })
+                .collect(),
+        });
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LevelError>
+    where
+        K: Serialize,
+    {
+        let path = path.as_ref();
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| LevelError::Parse(path.to_path_buf(), e))?;
+        std::fs::write(path, text).map_err(|e| LevelError::Io(path.to_path_buf(), e))
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError>
+    where
+        K: DeserializeOwned,
+    {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).map_err(|e| LevelError::Io(path.to_path_buf(), e))?;
+        let file: Self =
+            serde_json::from_str(&text).map_err(|e| LevelError::Parse(path.to_path_buf(), e))?;
+        if file.version > LEVEL_FORMAT_VERSION {
+            return Err(LevelError::Version(file.version));
+        }
+        Ok(file)
+    }
+    /// Load the tileset images (relative to `dir`) and make the level's tilesets, tilemaps, and spawn points.
+    pub fn build(&self, dir: impl AsRef<Path>) -> Result<Level<K>, LevelError>
+    where
+        K: Clone,
+    {
+        let dir = dir.as_ref();
+        let mut tilesets = vec![];
+        for (i, data) in self.tilesets.iter().enumerate() {
+            tilesets.push(Rc::new(data.build(dir).map_err(|e| match e {
+                LevelError::Invalid(what) => LevelError::Invalid(format!("tileset {}: {}", i, what)),
+                e => e,
+            })?));
+        }
+        let mut tilemaps = vec![];
+        for (i, data) in self.tilemaps.iter().enumerate() {
+            let tileset = tilesets.get(data.tileset).ok_or_else(|| {
+                LevelError::Invalid(format!("tilemap {} uses missing tileset {}", i, data.tileset))
+            })?;
+            if data.tiles.len() != data.dims.0 * data.dims.1 {
+                return Err(LevelError::Invalid(format!("tilemap {} is the wrong size", i)));
+            }
+            if let Some(bad) = data.tiles.iter().flatten().find(|t| **t >= tileset.tiles.len()) {
+                return Err(LevelError::Invalid(format!("tilemap {} uses missing tile {}", i, bad)));
+            }
+            let ids = data
+                .tiles
+                .iter()
+                .map(|t| t.map_or(TileID::EMPTY, TileID::new))
+                .collect();
+            tilemaps.push(Tilemap::from_ids(data.position, data.dims, tileset, ids));
+        }
+        Ok(Level {
+            tilesets,
+            tilemaps,
+            spawns: self.spawns.clone(),
+        })
+    }
+}
+impl<K> Default for LevelFile<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl TilesetData {
+    fn build(&self, dir: &Path) -> Result<Tileset, LevelError> {
+        let n = self.tiles.len();
+        let missing = |what: &str, id: &usize| {
+            Err(LevelError::Invalid(format!("{} refers to missing tile {}", what, id)))
+        };
+        for (id, frames) in self.animations.iter() {
+            if let Some(bad) = std::iter::once(id).chain(frames.iter().map(|(f, _)| f)).find(|t| **t >= n) {
+                return missing("animation", bad);
+            }
+            if frames.iter().map(|(_, d)| d).sum::<usize>() == 0 {
+                return Err(LevelError::Invalid(format!("animation of tile {} never lasts a tick", id)));
+            }
+        }
+        if let Some(bad) = self.tags.keys().find(|t| **t >= n) {
+            return missing("tag", bad);
+        }
+        for t in self.terrains.values() {
+            if let Some(bad) = std::iter::once(&t.fallback).chain(t.rules.values()).find(|t| **t >= n) {
+                return missing("terrain", bad);
+            }
+        }
+        if self.grid.tile_w == 0 || self.grid.tile_h == 0 {
+            return Err(LevelError::Invalid("tiles must have a size".to_string()));
+        }
+        let path = dir.join(&self.image);
+        let image = image::open(&path)
+            .map_err(|e| LevelError::Image(path.clone(), e))?
+            .into_rgba8();
+        if (image.width() as usize) < 2 * self.grid.margin + self.grid.tile_w {
+            return Err(LevelError::Invalid(format!("{} is too narrow for its tiles", self.image)));
+        }
+        let mut tileset = Tileset::with_grid(self.tiles.clone(), &Rc::new(Texture::new(image)), self.grid);
+        for (id, frames) in self.animations.iter() {
+            tileset.add_animation(*id, frames.clone());
+        }
+        for (id, tags) in self.tags.iter() {
+            for (name, value) in tags.iter() {
+                tileset.set_tag(*id, name, value.clone());
+            }
+        }
+        for (name, terrain) in self.terrains.iter() {
+            tileset.add_terrain(name, terrain.clone());
+        }
+        Ok(tileset)
+    }
+}
@@ -20,11 +20,22 @@ pub mod resources;
 use resources::Resources;
 pub mod animation;
 pub mod collision;
+pub mod shapes;
 pub mod texture;
 pub mod tiles;
+pub mod layers;
+pub mod chunks;
+pub mod level;
+pub mod tiled;
+pub mod pathfinding;
+pub mod raycast;
+pub mod physics;
+pub mod controller;
+pub mod query;
 
 const DEPTH: usize = 4;
-const DT: f64 = 1.0 / 60.0;
+/// How long each call to `update` in `run` simulates, in seconds
+pub const DT: f64 = 1.0 / 60.0;
 
 pub fn run<Rule, State>(
     width: usize,
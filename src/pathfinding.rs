@@ -0,0 +1,460 @@
+//! A* search and flow fields over the tiles of a `Tilemap`.
+//!
+//! Step costs are in tenths of a tile so diagonal steps can cost 14 instead of 10.
+//! With a custom cost callback each tile's cost multiplies that, so e.g. a cost of 3 makes mud three times as slow.
+use crate::tiles::*;
+use crate::types::*;
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Which neighbours of a tile a path can step to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Connectivity {
+    /// Up, down, left and right
+    Four,
+    /// Diagonals too
+    Eight,
+}
+/// When a diagonal step may squeeze past the corners of the two tiles beside it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Corners {
+    /// Diagonal steps can go between two blocked tiles
+    Always,
+    /// Diagonal steps can clip one blocked corner but not go between two
+    IfOneOpen,
+    /// Diagonal steps need both tiles beside them to be open, so paths never clip corners
+    Never,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PathOptions {
+    pub connectivity: Connectivity,
+    pub corners: Corners,
+    /// How many tiles wide and tall the thing following the path is.
+    /// Path positions are its top-left tile, and every tile it covers must be passable.
+    pub size: (usize, usize),
+}
+impl PathOptions {
+    /// Options for something `size` pixels big on `map`'s tiles, which may cover several tiles
+    pub fn for_entity(map: &Tilemap, connectivity: Connectivity, size: (usize, usize)) -> Self {
+        let (tw, th) = map.tile_size();
+        Self {
+            connectivity,
+            corners: Corners::Never,
+            size: (
+                ((size.0 + tw - 1) / tw).max(1),
+                ((size.1 + th - 1) / th).max(1),
+            ),
+        }
+    }
+}
+impl Default for PathOptions {
+    fn default() -> Self {
+        Self {
+            connectivity: Connectivity::Four,
+            corners: Corners::Never,
+            size: (1, 1),
+        }
+    }
+}
+
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+/// The shortest path from start to goal which avoids solid tiles, including both ends.
+/// Empty tiles are open; tiles off the map are blocked.
+pub fn find_path(
+    map: &Tilemap,
+    start: TileCoord,
+    goal: TileCoord,
+    opts: &PathOptions,
+) -> Option<Vec<TileCoord>> {
+    find_path_with(map, start, goal, opts, solid_cost)
+}
+
+/// Like `find_path`, but `cost` says how expensive each tile is to enter (None for impassable).
+/// It's given None for empty tiles. Costs below 1 are treated as 1.
+pub fn find_path_with(
+    map: &Tilemap,
+    start: TileCoord,
+    goal: TileCoord,
+    opts: &PathOptions,
+    cost: impl Fn(Option<Tile>) -> Option<u32>,
+) -> Option<Vec<TileCoord>> {
+    assert!(opts.size.0 > 0 && opts.size.1 > 0, "Paths need a footprint at least one tile big");
+    let (w, h) = map.size();
+    let footprint = |c: TileCoord| footprint_cost(map, c, opts.size, &cost);
+    footprint(start)?;
+    footprint(goal)?;
+    let idx = |TileCoord(x, y): TileCoord| y as usize * w + x as usize;
+    let coord = |i: usize| TileCoord((i % w) as i32, (i / w) as i32);
+    let heuristic = |TileCoord(x, y): TileCoord| {
+        let dx = (x - goal.0).unsigned_abs();
+        let dy = (y - goal.1).unsigned_abs();
+        match opts.connectivity {
+            Connectivity::Four => STRAIGHT * (dx + dy),
+            Connectivity::Eight => {
+                STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
+            }
+        }
+    };
+    let steps = steps(opts.connectivity);
+    let mut best = vec![u32::MAX; w * h];
+    let mut came_from = vec![usize::MAX; w * h];
+    let mut open = BinaryHeap::new();
+    best[idx(start)] = 0;
+    open.push(Reverse((heuristic(start), 0, idx(start))));
+    while let Some(Reverse((_f, g, i))) = open.pop() {
+        if g > best[i] {
+            // A cheaper way here was already expanded
+            continue;
+        }
+        let here = coord(i);
+        if here == goal {
+            let mut path = vec![here];
+            let mut i = i;
+            while came_from[i] != usize::MAX {
+                i = came_from[i];
+                path.push(coord(i));
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for &(dx, dy) in steps {
+            let next = TileCoord(here.0 + dx, here.1 + dy);
+            let enter = match footprint(next) {
+                Some(c) => c,
+                None => continue,
+            };
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal
+                && !corner_ok(
+                    opts.corners,
+                    footprint(TileCoord(here.0 + dx, here.1)).is_some(),
+                    footprint(TileCoord(here.0, here.1 + dy)).is_some(),
+                )
+            {
+                continue;
+            }
+            let step = if diagonal { DIAGONAL } else { STRAIGHT };
+            // Saturate so huge callback costs can't wrap around into cheap ones
+            let g2 = g.saturating_add(step.saturating_mul(enter));
+            let n = idx(next);
+            if g2 < best[n] {
+                best[n] = g2;
+                came_from[n] = i;
+                open.push(Reverse((g2.saturating_add(heuristic(next)), g2, n)));
+            }
+        }
+    }
+    None
+}
+
+/// The cost of something `size` tiles big standing with its top-left on tile c: that of its most expensive tile
+fn footprint_cost(
+    map: &Tilemap,
+    TileCoord(x, y): TileCoord,
+    (fw, fh): (usize, usize),
+    cost: impl Fn(Option<Tile>) -> Option<u32>,
+) -> Option<u32> {
+    let (w, h) = map.size();
+    if x < 0 || y < 0 || x as usize + fw > w || y as usize + fh > h {
+        return None;
+    }
+    let mut worst = 1;
+    for ty in y..y + fh as i32 {
+        for tx in x..x + fw as i32 {
+            let id = map.get_tile(tx as usize, ty as usize).ok()?;
+            let tile = if id.is_empty() {
+                None
+            } else {
+                Some(map.tileset()[id])
+            };
+            worst = worst.max(cost(tile)?);
+        }
+    }
+    Some(worst)
+}
+fn steps(connectivity: Connectivity) -> &'static [(i32, i32)] {
+    match connectivity {
+        Connectivity::Four => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+        Connectivity::Eight => &[
+            (0, -1),
+            (1, 0),
+            (0, 1),
+            (-1, 0),
+            (1, -1),
+            (1, 1),
+            (-1, 1),
+            (-1, -1),
+        ],
+    }
+}
+/// Whether a diagonal step is allowed, given whether each of the two tiles beside it is open
+fn corner_ok(corners: Corners, side_a: bool, side_b: bool) -> bool {
+    match corners {
+        Corners::Always => true,
+        Corners::IfOneOpen => side_a || side_b,
+        Corners::Never => side_a && side_b,
+    }
+}
+fn solid_cost(tile: Option<Tile>) -> Option<u32> {
+    if tile.map_or(false, |t| t.solid) {
+        None
+    } else {
+        Some(1)
+    }
+}
+
+/// The world positions of a path's tiles' top-left corners, i.e. where something following it should put its own top-left corner
+pub fn world_path(map: &Tilemap, path: &[TileCoord]) -> Vec<Vec2i> {
+    path.iter()
+        .map(|c| c.to_world(map.position, map.tile_size()))
+        .collect()
+}
+
+/// The direction to step from every tile of a map to reach one goal tile by the cheapest route.
+/// Building it costs about as much as one A* search, but then any number of chasers can look up which way to go.
+pub struct FlowField {
+    position: Vec2i,
+    dims: (usize, usize),
+    tile_size: (usize, usize),
+    opts: PathOptions,
+    goal: TileCoord,
+    // What it costs to enter each tile, or None if it's impassable
+    costs: Vec<Option<u32>>,
+    // The cost of the cheapest route from each tile to the goal, or u32::MAX if there isn't one
+    dist: Vec<u32>,
+    // Which way to step from each tile; (0, 0) at the goal and wherever the goal can't be reached
+    dirs: Vec<(i8, i8)>,
+}
+impl FlowField {
+    /// A flow field over `map` which avoids solid tiles
+    pub fn new(map: &Tilemap, goal: TileCoord, opts: &PathOptions) -> Self {
+        Self::with_cost(map, goal, opts, solid_cost)
+    }
+    /// A flow field where `cost` says how expensive each tile is to enter, as in `find_path_with`
+    pub fn with_cost(
+        map: &Tilemap,
+        goal: TileCoord,
+        opts: &PathOptions,
+        cost: impl Fn(Option<Tile>) -> Option<u32>,
+    ) -> Self {
+        assert!(opts.size.0 > 0 && opts.size.1 > 0, "Flow fields need a footprint at least one tile big");
+        let (w, h) = map.size();
+        let costs = (0..w * h)
+            .map(|i| footprint_cost(map, TileCoord((i % w) as i32, (i / w) as i32), opts.size, &cost))
+            .collect();
+        let mut field = Self {
+            position: map.position,
+            dims: (w, h),
+            tile_size: map.tile_size(),
+            opts: *opts,
+            goal,
+            costs,
+            dist: vec![],
+            dirs: vec![],
+        };
+        field.rebuild();
+        field
+    }
+    pub fn goal(&self) -> TileCoord {
+        self.goal
+    }
+    /// Move the goal, e.g. when the player steps onto a new tile. This recomputes the whole field.
+    pub fn set_goal(&mut self, goal: TileCoord) {
+        if goal != self.goal {
+            self.goal = goal;
+            self.rebuild();
+        }
+    }
+    /// The cost of the cheapest route from c to the goal, in the units described at the top of this module
+    pub fn distance(&self, c: TileCoord) -> Option<u32> {
+        self.index(c)
+            .map(|i| self.dist[i])
+            .filter(|d| *d != u32::MAX)
+    }
+    /// Which way (-1, 0 or 1 on each axis) to step from tile c, or None if the goal can't be reached from there
+    pub fn direction(&self, c: TileCoord) -> Option<(i32, i32)> {
+        self.distance(c)?;
+        let (dx, dy) = self.dirs[self.index(c)?];
+        Some((dx as i32, dy as i32))
+    }
+    /// Which way to step from the tile containing world position posn
+    pub fn direction_at(&self, posn: Vec2i) -> Option<(i32, i32)> {
+        self.direction(TileCoord::from_world(posn, self.position, self.tile_size))
+    }
+    /// Bring the field up to date after the tiles in `region` of `map` changed (e.g. from `Tilemap::take_dirty`).
+    /// Only the routes that went through or can now go through the region are recomputed.
+    pub fn update(&mut self, map: &Tilemap, region: TileRect) {
+        self.update_with(map, region, solid_cost)
+    }
+    /// Like `update`, for fields built with `with_cost`; pass the same cost function.
+    pub fn update_with(
+        &mut self,
+        map: &Tilemap,
+        region: TileRect,
+        cost: impl Fn(Option<Tile>) -> Option<u32>,
+    ) {
+        let (w, h) = self.dims;
+        let (fw, fh) = self.opts.size;
+        // Anything whose footprint overlaps the region might cost something different now
+        let x0 = region.x.saturating_sub(fw - 1);
+        let y0 = region.y.saturating_sub(fh - 1);
+        let x1 = (region.x + region.w).min(w);
+        let y1 = (region.y + region.h).min(h);
+        let mut changed = vec![];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let i = y * w + x;
+                let c = footprint_cost(map, TileCoord(x as i32, y as i32), self.opts.size, &cost);
+                if c != self.costs[i] {
+                    self.costs[i] = c;
+                    changed.push(i);
+                }
+            }
+        }
+        if changed.is_empty() {
+            return;
+        }
+        // Forget every route that led through a changed tile...
+        let mut invalid = vec![false; w * h];
+        let mut stack = changed.clone();
+        for i in changed.iter() {
+            invalid[*i] = true;
+        }
+        // Tiles next to a changed tile might now have a cheaper route through it
+        let mut touched: Vec<usize> = vec![];
+        while let Some(i) = stack.pop() {
+            touched.push(i);
+            self.dist[i] = u32::MAX;
+            for n in self.neighbours(i).collect::<Vec<_>>() {
+                touched.push(n);
+                if !invalid[n] && self.relies_on(n, i) {
+                    invalid[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        // ...then rebuild them from the routes that are still good
+        let mut open = BinaryHeap::new();
+        for &i in touched.iter() {
+            if !invalid[i] {
+                if self.dist[i] != u32::MAX {
+                    open.push(Reverse((self.dist[i], i)));
+                }
+                continue;
+            }
+            if self.coord(i) == self.goal && self.costs[i].is_some() {
+                self.dist[i] = 0;
+                open.push(Reverse((0, i)));
+            }
+        }
+        self.flood(open, &mut touched);
+        touched.sort_unstable();
+        touched.dedup();
+        for i in touched {
+            self.dirs[i] = self.best_step(i);
+            for n in self.neighbours(i).collect::<Vec<_>>() {
+                self.dirs[n] = self.best_step(n);
+            }
+        }
+    }
+    fn rebuild(&mut self) {
+        let (w, h) = self.dims;
+        self.dist = vec![u32::MAX; w * h];
+        self.dirs = vec![(0, 0); w * h];
+        let mut open = BinaryHeap::new();
+        if let Some(g) = self.index(self.goal).filter(|g| self.costs[*g].is_some()) {
+            self.dist[g] = 0;
+            open.push(Reverse((0, g)));
+        }
+        self.flood(open, &mut vec![]);
+        for i in 0..w * h {
+            self.dirs[i] = self.best_step(i);
+        }
+    }
+    /// Dijkstra outwards from the goal: a tile's distance is the cheapest step into a neighbour plus that neighbour's distance.
+    /// Every tile whose distance changes is added to `touched`.
+    fn flood(&mut self, mut open: BinaryHeap<Reverse<(u32, usize)>>, touched: &mut Vec<usize>) {
+        while let Some(Reverse((d, i))) = open.pop() {
+            if d > self.dist[i] {
+                continue;
+            }
+            let enter = match self.costs[i] {
+                Some(c) => c,
+                None => continue,
+            };
+            for n in self.neighbours(i).collect::<Vec<_>>() {
+                if self.costs[n].is_none() || !self.can_step(n, i) {
+                    continue;
+                }
+                let d2 = d.saturating_add(self.step_len(n, i).saturating_mul(enter));
+                if d2 < self.dist[n] {
+                    self.dist[n] = d2;
+                    touched.push(n);
+                    open.push(Reverse((d2, n)));
+                }
+            }
+        }
+    }
+    /// The step from tile i into the neighbour that continues its cheapest route
+    fn best_step(&self, i: usize) -> (i8, i8) {
+        if self.dist[i] == u32::MAX || self.coord(i) == self.goal {
+            return (0, 0);
+        }
+        let here = self.coord(i);
+        self.neighbours(i)
+            .filter(|n| self.dist[*n] != u32::MAX && self.can_step(i, *n))
+            .filter_map(|n| Some((self.dist[n].saturating_add(self.step_len(i, n).saturating_mul(self.costs[n]?)), n)))
+            .min()
+            .map_or((0, 0), |(_d, n)| {
+                let there = self.coord(n);
+                ((there.0 - here.0) as i8, (there.1 - here.1) as i8)
+            })
+    }
+    /// Whether tile i's flow leads into tile n, or squeezes past n's corner on a diagonal
+    fn relies_on(&self, i: usize, n: usize) -> bool {
+        let (dx, dy) = (self.dirs[i].0 as i32, self.dirs[i].1 as i32);
+        let here = self.coord(i);
+        let there = self.coord(n);
+        if (dx, dy) == (0, 0) {
+            return false;
+        }
+        there == TileCoord(here.0 + dx, here.1 + dy)
+            || (dx != 0 && dy != 0
+                && (there == TileCoord(here.0 + dx, here.1) || there == TileCoord(here.0, here.1 + dy)))
+    }
+    fn can_step(&self, from: usize, to: usize) -> bool {
+        let (a, b) = (self.coord(from), self.coord(to));
+        if a.0 == b.0 || a.1 == b.1 {
+            return true;
+        }
+        let open = |c: TileCoord| self.index(c).map_or(false, |i| self.costs[i].is_some());
+        corner_ok(self.opts.corners, open(TileCoord(b.0, a.1)), open(TileCoord(a.0, b.1)))
+    }
+    fn step_len(&self, from: usize, to: usize) -> u32 {
+        let (a, b) = (self.coord(from), self.coord(to));
+        if a.0 == b.0 || a.1 == b.1 {
+            STRAIGHT
+        } else {
+            DIAGONAL
+        }
+    }
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        let here = self.coord(i);
+        steps(self.opts.connectivity)
+            .iter()
+            .filter_map(move |(dx, dy)| self.index(TileCoord(here.0 + dx, here.1 + dy)))
+    }
+    fn index(&self, TileCoord(x, y): TileCoord) -> Option<usize> {
+        if x >= 0 && y >= 0 && (x as usize) < self.dims.0 && (y as usize) < self.dims.1 {
+            Some(y as usize * self.dims.0 + x as usize)
+        } else {
+            None
+        }
+    }
+    fn coord(&self, i: usize) -> TileCoord {
+        TileCoord((i % self.dims.0) as i32, (i / self.dims.0) as i32)
+    }
+}
@@ -0,0 +1,93 @@
+//! Sub-pixel motion: bodies with fractional positions and velocities, stepped at a fixed rate.
+//!
+//! Bodies sit alongside the usual `positions: Vec<Vec2i>`. Each step, `integrate` moves the bodies and
+//! writes their rounded positions there for drawing and collision; once collision has pushed things
+//! around, `sync_bodies` copies those pushes back so bodies stop when they hit something.
+use crate::types::*;
+
+/// How long one physics step is, in seconds: one tick of `engine2d::run`
+pub const STEP: f32 = crate::DT as f32;
+
+/// Something that moves. Speeds are in pixels per second and accelerations in pixels per second per second.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Body {
+    /// The top-left corner of the body's box, in fractional pixels
+    pub position: Vec2f,
+    pub velocity: Vec2f,
+    /// How the body is accelerating on its own, e.g. from input; gravity is added to this
+    pub acceleration: Vec2f,
+    /// How strongly gravity pulls on this body: 0 for things that float
+    pub gravity_scale: f32,
+    /// The fraction of its velocity the body loses each second, like air resistance
+    pub drag: f32,
+    /// How quickly the body slows to a stop on an axis where it isn't accelerating
+    pub friction: f32,
+    /// The fastest the body can go on each axis
+    pub max_speed: Vec2f,
+}
+impl Body {
+    /// A body at rest at posn, with gravity and no speed limit
+    pub fn new(posn: Vec2i) -> Self {
+        Self {
+            position: posn.into(),
+            velocity: Vec2f(0.0, 0.0),
+            acceleration: Vec2f(0.0, 0.0),
+            gravity_scale: 1.0,
+            drag: 0.0,
+            friction: 0.0,
+            max_speed: Vec2f(f32::INFINITY, f32::INFINITY),
+        }
+    }
+    /// The whole pixel the body is drawn and collided at
+    pub fn pixel(&self) -> Vec2i {
+        self.position.to_vec2i()
+    }
+    /// Advance the body by dt seconds
+    pub fn step(&mut self, gravity: Vec2f, dt: f32) {
+        let accel = self.acceleration + gravity * self.gravity_scale;
+        self.velocity += accel * dt;
+        self.velocity *= (1.0 - self.drag * dt).max(0.0);
+        let slow = |v: f32, a: f32| {
+            if a != 0.0 {
+                return v;
+            }
+            // Friction only ever brings things to a stop, never pushes them back the other way
+            let dv = self.friction * dt;
+            if v.abs() <= dv {
+                0.0
+            } else {
+                v - dv * v.signum()
+            }
+        };
+        self.velocity = Vec2f(slow(self.velocity.0, accel.0), slow(self.velocity.1, accel.1));
+        self.velocity = Vec2f(
+            self.velocity.0.clamp(-self.max_speed.0, self.max_speed.0),
+            self.velocity.1.clamp(-self.max_speed.1, self.max_speed.1),
+        );
+        self.position += self.velocity * dt;
+    }
+}
+
+/// Step every body once by `STEP` and write their pixel positions into positions
+pub fn integrate(bodies: &mut [Body], positions: &mut [Vec2i], gravity: Vec2f) {
+    for (body, posn) in bodies.iter_mut().zip(positions.iter_mut()) {
+        body.step(gravity, STEP);
+        *posn = body.pixel();
+    }
+}
+
+/// Bring bodies up to date with collision's pushes to positions. A body pushed against the way it was
+/// moving on an axis has hit something, so it stops on that axis; its sub-pixel remainder is kept.
+pub fn sync_bodies(bodies: &mut [Body], positions: &[Vec2i]) {
+    for (body, posn) in bodies.iter_mut().zip(positions.iter()) {
+        let px = body.pixel();
+        let push = (posn.0 - px.0, posn.1 - px.1);
+        body.position += Vec2f(push.0 as f32, push.1 as f32);
+        if push.0 as f32 * body.velocity.0 < 0.0 {
+            body.velocity.0 = 0.0;
+        }
+        if push.1 as f32 * body.velocity.1 < 0.0 {
+            body.velocity.1 = 0.0;
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Asking what's at a point, in a rect or along a ray, without gathering contacts.
+//!
+//! Queries look through the usual parallel vecs of entities plus the level's tilemaps, and only find
+//! things on the layers in their mask. Tiles count if contacts would be made for them: solid or hazard tiles.
+//! Every entity is checked, so for lots of entities and lots of queries keep a `SpatialHash` instead.
+use crate::collision::Collider;
+use crate::raycast::raycast_with;
+use crate::shapes::{overlap, rect_box, Placed};
+use crate::tiles::*;
+use crate::types::*;
+
+/// Something a query found
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hit {
+    /// The entity at this index
+    Entity(usize),
+    /// A tile of the tilemap at index `map`, with its world-space rect
+    Tile { map: usize, coord: TileCoord, tile: Tile, rect: Rect },
+}
+
+/// Where a `World::raycast` ran into something
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WorldRayHit {
+    pub hit: Hit,
+    /// Where the ray met it, in world pixels
+    pub point: Vec2f,
+    /// The unit normal of the surface it hit, pointing back towards the ray; (0, 0) if the ray started inside
+    pub normal: Vec2f,
+    /// How far along the ray the hit is, from 0.0 at `from` to 1.0 at `to`
+    pub t: f32,
+}
+
+/// Everything queries can find: a level's entities and tiles
+#[derive(Clone, Copy)]
+pub struct World<'a> {
+    pub positions: &'a [Vec2i],
+    pub sizes: &'a [(usize, usize)],
+    pub colliders: &'a [Collider],
+    pub tilemaps: &'a [Tilemap],
+    /// The layers the tiles are on, for matching against queries' masks
+    pub tile_layer: u32,
+}
+impl<'a> World<'a> {
+    pub fn new(
+        positions: &'a [Vec2i],
+        sizes: &'a [(usize, usize)],
+        colliders: &'a [Collider],
+        tilemaps: &'a [Tilemap],
+        tile_layer: u32,
+    ) -> Self {
+        assert_eq!(positions.len(), sizes.len());
+        assert_eq!(positions.len(), colliders.len());
+        Self {
+            positions,
+            sizes,
+            colliders,
+            tilemaps,
+            tile_layer,
+        }
+    }
+    /// Everything on mask's layers covering the pixel at p, e.g. under the mouse
+    pub fn overlap_point(&self, p: Vec2i, mask: u32) -> Vec<Hit> {
+        self.overlap_rect(Rect::new(p.0, p.1, 1, 1), mask)
+    }
+    /// Everything on mask's layers overlapping r; just touching its edge doesn't count
+    pub fn overlap_rect(&self, r: Rect, mask: u32) -> Vec<Hit> {
+        let mut hits = vec![];
+        if r.w == 0 || r.h == 0 {
+            return hits;
+        }
+        for i in self.entities(mask) {
+            if overlap(self.placed(i), rect_box(r)).map_or(false, |(_, depth)| depth > 0.0) {
+                hits.push(Hit::Entity(i));
+            }
+        }
+        if self.tile_layer & mask == 0 {
+            return hits;
+        }
+        for (m, map) in self.tilemaps.iter().enumerate() {
+            let TileCoord(x0, y0) = map.tile_coord(Vec2i(r.x, r.y));
+            let TileCoord(x1, y1) = map.tile_coord(Vec2i(r.x + r.w as i32 - 1, r.y + r.h as i32 - 1));
+            for ty in y0..=y1 {
+                for tx in x0..=x1 {
+                    let coord = TileCoord(tx, ty);
+                    if let Some((tile, rect)) = map.tile_at_coord(coord).filter(|(t, _)| t.solid || t.hazard) {
+                        hits.push(Hit::Tile { map: m, coord, tile, rect });
+                    }
+                }
+            }
+        }
+        hits
+    }
+    /// The first thing on mask's layers on the segment from `from` to `to`, if any
+    pub fn raycast(&self, from: Vec2i, to: Vec2i, mask: u32) -> Option<WorldRayHit> {
+        let (o, d) = (Vec2f::from(from), Vec2f::from(to - from));
+        let mut best: Option<WorldRayHit> = None;
+        let mut consider = |hit: WorldRayHit| {
+            if best.map_or(true, |b| hit.t < b.t) {
+                best = Some(hit);
+            }
+        };
+        for i in self.entities(mask) {
+            if let Some((t, normal)) = ray_placed(o, d, self.placed(i)) {
+                consider(WorldRayHit { hit: Hit::Entity(i), point: o + d * t, normal, t });
+            }
+        }
+        if self.tile_layer & mask != 0 {
+            for (m, map) in self.tilemaps.iter().enumerate() {
+                if let Some(h) = raycast_with(map, from, to, |t| t.solid || t.hazard) {
+                    consider(WorldRayHit {
+                        hit: Hit::Tile { map: m, coord: h.coord, tile: h.tile, rect: h.rect },
+                        point: h.point,
+                        normal: Vec2f(h.normal.0 as f32, h.normal.1 as f32),
+                        t: h.t,
+                    });
+                }
+            }
+        }
+        best
+    }
+    fn entities(&self, mask: u32) -> impl Iterator<Item = usize> + '_ {
+        (0..self.positions.len()).filter(move |&i| self.colliders[i].layer & mask != 0)
+    }
+    fn placed(&self, i: usize) -> Placed {
+        self.colliders[i].shape.place(self.positions[i].into(), self.sizes[i])
+    }
+}
+
+// When the ray o + d*t (for t in 0..=1) first meets s, and the normal there
+fn ray_placed(o: Vec2f, d: Vec2f, s: Placed) -> Option<(f32, Vec2f)> {
+    match s {
+        Placed::Box(l, t, r, b) => ray_box(o, d, (l, t, r, b)),
+        // A capsule is a box between its ends plus a circle at each end, so the ray meets whichever comes first.
+        // The ends go first so a ray starting inside one wins ties with the box, which for a circle has no height.
+        Placed::Round(p0, p1, r) => {
+            let mid = if p0.0 == p1.0 {
+                (p0.0 - r, p0.1.min(p1.1), p0.0 + r, p0.1.max(p1.1))
+            } else {
+                (p0.0.min(p1.0), p0.1 - r, p0.0.max(p1.0), p0.1 + r)
+            };
+            [ray_circle(o, d, p0, r), ray_circle(o, d, p1, r), ray_box(o, d, mid)]
+                .iter()
+                .flatten()
+                .copied()
+                .fold(None, |best: Option<(f32, Vec2f)>, h| if best.map_or(true, |b| h.0 < b.0) { Some(h) } else { best })
+        }
+    }
+}
+
+fn ray_box(o: Vec2f, d: Vec2f, (l, t, r, b): (f32, f32, f32, f32)) -> Option<(f32, Vec2f)> {
+    // Where the ray is between each pair of sides, and which side it crossed going in
+    let slab = |o: f32, d: f32, lo: f32, hi: f32| {
+        if d == 0.0 {
+            if o < lo || o > hi {
+                None
+            } else {
+                Some((f32::NEG_INFINITY, f32::INFINITY, 0.0))
+            }
+        } else {
+            let (t0, t1) = ((lo - o) / d, (hi - o) / d);
+            Some((t0.min(t1), t0.max(t1), -d.signum()))
+        }
+    };
+    let (x0, x1, nx) = slab(o.0, d.0, l, r)?;
+    let (y0, y1, ny) = slab(o.1, d.1, t, b)?;
+    let (enter, exit) = (x0.max(y0), x1.min(y1));
+    if enter > exit || exit < 0.0 || enter > 1.0 {
+        return None;
+    }
+    if enter < 0.0 {
+        return Some((0.0, Vec2f(0.0, 0.0)));
+    }
+    Some((enter, if x0 > y0 { Vec2f(nx, 0.0) } else { Vec2f(0.0, ny) }))
+}
+
+fn ray_circle(o: Vec2f, d: Vec2f, c: Vec2f, r: f32) -> Option<(f32, Vec2f)> {
+    // A circle with no radius is just a point, which the box between a capsule's ends already covers,
+    // and it has no normal to divide out
+    if r <= 0.0 {
+        return None;
+    }
+    let m = o - c;
+    let k = m.dot(m) - r * r;
+    if k <= 0.0 {
+        return Some((0.0, Vec2f(0.0, 0.0)));
+    }
+    let (a, b) = (d.dot(d), m.dot(d));
+    let disc = b * b - a * k;
+    if a == 0.0 || b > 0.0 || disc < 0.0 {
+        return None;
+    }
+    let t = (-b - disc.sqrt()) / a;
+    if t > 1.0 {
+        return None;
+    }
+    Some((t, (m + d * t) * (1.0 / r)))
+}
@@ -0,0 +1,142 @@
+//! Ray casts and line-of-sight checks against a `Tilemap`'s solid tiles.
+//!
+//! Rays walk the grid one tile at a time (DDA), so a long ray costs about one step per tile it crosses.
+use crate::tiles::*;
+use crate::types::*;
+
+/// Where a ray ran into a tile
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RayHit {
+    pub tile: Tile,
+    pub coord: TileCoord,
+    /// The world-space rectangle of the tile that was hit
+    pub rect: Rect,
+    /// Where the ray met the tile's edge, in world pixels
+    pub point: Vec2f,
+    /// Which face of the tile was hit, pointing back towards the ray: e.g. (-1, 0) for its left side.
+    /// (0, 0) if the ray started inside the tile.
+    pub normal: (i32, i32),
+    /// How far along the ray the hit is, from 0.0 at `from` to 1.0 at `to`
+    pub t: f32,
+}
+
+/// The first solid tile on the segment from `from` to `to`, if any
+pub fn raycast(map: &Tilemap, from: Vec2i, to: Vec2i) -> Option<RayHit> {
+    raycast_with(map, from, to, |t| t.solid)
+}
+
+/// Whether nothing solid is between from and to
+pub fn line_of_sight(map: &Tilemap, from: Vec2i, to: Vec2i) -> bool {
+    raycast(map, from, to).is_none()
+}
+
+/// Like `raycast`, but `blocks` decides which tiles stop the ray (e.g. solid tiles which aren't one-way).
+/// Empty cells and the area off the map never block.
+pub fn raycast_with(
+    map: &Tilemap,
+    from: Vec2i,
+    to: Vec2i,
+    blocks: impl Fn(&Tile) -> bool,
+) -> Option<RayHit> {
+    let (tw, th) = map.tile_size();
+    let (tw, th) = (tw as f32, th as f32);
+    // Work in tile units relative to the map's corner
+    let ox = (from.0 - map.position.0) as f32 / tw;
+    let oy = (from.1 - map.position.1) as f32 / th;
+    let dx = (to.0 - from.0) as f32 / tw;
+    let dy = (to.1 - from.1) as f32 / th;
+    let mut coord = map.tile_coord(from);
+    let last = map.tile_coord(to);
+    let step_x = if dx > 0.0 { 1 } else { -1 };
+    let step_y = if dy > 0.0 { 1 } else { -1 };
+    // How far along the ray (0..1) we go to cross one whole tile in each axis
+    let t_delta_x = if dx != 0.0 { (1.0 / dx).abs() } else { f32::INFINITY };
+    let t_delta_y = if dy != 0.0 { (1.0 / dy).abs() } else { f32::INFINITY };
+    // How far along the ray the next vertical and horizontal tile edges are
+    let next_edge = |o: f32, c: i32, step: i32| if step > 0 { (c + 1) as f32 - o } else { o - c as f32 };
+    let mut t_max_x = if dx != 0.0 { next_edge(ox, coord.0, step_x) * t_delta_x } else { f32::INFINITY };
+    let mut t_max_y = if dy != 0.0 { next_edge(oy, coord.1, step_y) * t_delta_y } else { f32::INFINITY };
+    let mut t = 0.0;
+    let mut normal = (0, 0);
+    // Every step moves one tile closer to the last tile, so this many steps always gets there
+    let steps = (last.0 - coord.0).abs() + (last.1 - coord.1).abs();
+    for i in 0..=steps {
+        if i > 0 {
+            if t_max_x < t_max_y {
+                coord.0 += step_x;
+                t = t_max_x;
+                t_max_x += t_delta_x;
+                normal = (-step_x, 0);
+            } else {
+                coord.1 += step_y;
+                t = t_max_y;
+                t_max_y += t_delta_y;
+                normal = (0, -step_y);
+            }
+        }
+        if t > 1.0 {
+            break;
+        }
+        if let Some((tile, rect)) = map.tile_at_coord(coord) {
+            if blocks(&tile) {
+                let mut point = Vec2f(
+                    from.0 as f32 + (to.0 - from.0) as f32 * t,
+                    from.1 as f32 + (to.1 - from.1) as f32 * t,
+                );
+                // Put the point exactly on the face it hit, whatever rounding did
+                match normal {
+                    (-1, 0) => point.0 = rect.x as f32,
+                    (1, 0) => point.0 = (rect.x + rect.w as i32) as f32,
+                    (0, -1) => point.1 = rect.y as f32,
+                    (0, 1) => point.1 = (rect.y + rect.h as i32) as f32,
+                    _ => {}
+                }
+                return Some(RayHit {
+                    tile,
+                    coord,
+                    rect,
+                    point,
+                    normal,
+                    t,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// What something can see: everything within `range` pixels and `half_angle` radians of where it's facing,
+/// unless a solid tile is in the way.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VisionCone {
+    /// Which way the viewer is looking; it doesn't need to be normalized
+    pub facing: Vec2f,
+    pub half_angle: f32,
+    pub range: f32,
+}
+impl VisionCone {
+    pub fn new(facing: Vec2f, half_angle: f32, range: f32) -> Self {
+        Self {
+            facing,
+            half_angle,
+            range,
+        }
+    }
+    /// Whether a viewer with its eye at `eye` can see the point `target`
+    pub fn sees(&self, map: &Tilemap, eye: Vec2i, target: Vec2i) -> bool {
+        let (dx, dy) = ((target.0 - eye.0) as f32, (target.1 - eye.1) as f32);
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist > self.range {
+            return false;
+        }
+        let flen = (self.facing.0 * self.facing.0 + self.facing.1 * self.facing.1).sqrt();
+        // Right on top of us, or with no facing at all, counts as in view
+        if dist > 0.0 && flen > 0.0 {
+            let cos = (dx * self.facing.0 + dy * self.facing.1) / (dist * flen);
+            if cos < self.half_angle.cos() {
+                return false;
+            }
+        }
+        line_of_sight(map, eye, target)
+    }
+}
@@ -0,0 +1,164 @@
+//! Round collider shapes and the overlap tests between them and rects.
+//!
+//! A shape always fills an entity's box (its position and size), so the box still works for
+//! broadphases and tile lookups and only the final overlap test cares about the shape.
+use crate::types::*;
+
+/// Which shape fills an entity's box
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Shape {
+    /// The whole box
+    #[default]
+    Rect,
+    /// The biggest circle that fits in the middle of the box
+    Circle,
+    /// The box with its short ends fully rounded, like a pill lying along its long side
+    Capsule,
+}
+
+/// A shape placed in the world, in fractional pixels
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Placed {
+    /// Left, top, right, bottom
+    Box(f32, f32, f32, f32),
+    /// Everything within a radius of a horizontal or vertical segment; a circle if both ends are the same
+    Round(Vec2f, Vec2f, f32),
+}
+impl Shape {
+    pub(crate) fn place(self, Vec2f(x, y): Vec2f, (w, h): (usize, usize)) -> Placed {
+        let (w, h) = (w as f32, h as f32);
+        let (cx, cy) = (x + w / 2.0, y + h / 2.0);
+        let r = w.min(h) / 2.0;
+        match self {
+            Shape::Rect => Placed::Box(x, y, x + w, y + h),
+            Shape::Circle => Placed::Round(Vec2f(cx, cy), Vec2f(cx, cy), r),
+            Shape::Capsule if w > h => Placed::Round(Vec2f(x + r, cy), Vec2f(x + w - r, cy), r),
+            Shape::Capsule => Placed::Round(Vec2f(cx, y + r), Vec2f(cx, y + h - r), r),
+        }
+    }
+}
+pub(crate) fn rect_box(r: Rect) -> Placed {
+    Placed::Box(r.x as f32, r.y as f32, (r.x + r.w as i32) as f32, (r.y + r.h as i32) as f32)
+}
+
+/// How to push shape a (in the box at pos_a) out of shape b: a unit normal pointing from b towards a,
+/// and how far to push. None if they don't touch; a depth of 0 means they're just touching.
+pub fn shape_overlap(
+    a: Shape,
+    pos_a: Vec2f,
+    size_a: (usize, usize),
+    b: Shape,
+    pos_b: Vec2f,
+    size_b: (usize, usize),
+) -> Option<(Vec2f, f32)> {
+    overlap(a.place(pos_a, size_a), b.place(pos_b, size_b))
+}
+
+pub(crate) fn overlap(a: Placed, b: Placed) -> Option<(Vec2f, f32)> {
+    match (a, b) {
+        (Placed::Box(al, at, ar, ab), Placed::Box(bl, bt, br, bb)) => {
+            let x = ar.min(br) - al.max(bl);
+            let y = ab.min(bb) - at.max(bt);
+            if x < 0.0 || y < 0.0 {
+                return None;
+            }
+            // Same rule as rect_mtv: the axis with less overlap, away from b's center
+            if x > y {
+                let dy = (at + ab) - (bt + bb);
+                Some((Vec2f(0.0, if dy > 0.0 { 1.0 } else { -1.0 }), y))
+            } else {
+                let dx = (al + ar) - (bl + br);
+                Some((Vec2f(if dx > 0.0 { 1.0 } else { -1.0 }, 0.0), x))
+            }
+        }
+        (Placed::Round(p0, p1, r), Placed::Box(l, t, rr, bb)) => {
+            // The segment is horizontal or vertical, so its point nearest the box is found one axis at a time
+            let near = |e0: f32, e1: f32, mid: f32| mid.clamp(e0.min(e1), e0.max(e1));
+            let c = Vec2f(near(p0.0, p1.0, (l + rr) / 2.0), near(p0.1, p1.1, (t + bb) / 2.0));
+            circle_box(c, r, (l, t, rr, bb))
+        }
+        (Placed::Box(..), Placed::Round(..)) => overlap(b, a).map(|(n, d)| (Vec2f(-n.0, -n.1), d)),
+        (Placed::Round(a0, a1, ra), Placed::Round(b0, b1, rb)) => {
+            let (p, q) = closest_points(a0, a1, b0, b1);
+            let (dx, dy) = (p.0 - q.0, p.1 - q.1);
+            let dist = (dx * dx + dy * dy).sqrt();
+            if dist > ra + rb {
+                return None;
+            }
+            let normal = if dist > 0.0 { Vec2f(dx / dist, dy / dist) } else { Vec2f(-1.0, 0.0) };
+            Some((normal, ra + rb - dist))
+        }
+    }
+}
+
+fn circle_box(c: Vec2f, r: f32, (l, t, rr, bb): (f32, f32, f32, f32)) -> Option<(Vec2f, f32)> {
+    let q = Vec2f(c.0.clamp(l, rr), c.1.clamp(t, bb));
+    let (dx, dy) = (c.0 - q.0, c.1 - q.1);
+    let dist = (dx * dx + dy * dy).sqrt();
+    if dist > 0.0 {
+        return if dist > r { None } else { Some((Vec2f(dx / dist, dy / dist), r - dist)) };
+    }
+    // The center is inside the box, so leave by the nearest side
+    let sides = [
+        (c.0 - l, Vec2f(-1.0, 0.0)),
+        (rr - c.0, Vec2f(1.0, 0.0)),
+        (c.1 - t, Vec2f(0.0, -1.0)),
+        (bb - c.1, Vec2f(0.0, 1.0)),
+    ];
+    let (d, n) = sides
+        .iter()
+        .copied()
+        .fold(sides[0], |best, s| if s.0 < best.0 { s } else { best });
+    Some((n, d + r))
+}
+
+/// How far a has to move along the unit vector n to stop overlapping b, measured by projecting both onto n.
+/// This is exact when n is the normal `overlap` found, and stays right as a moves along it.
+pub(crate) fn depth_along(a: Placed, b: Placed, n: Vec2f) -> f32 {
+    let dot = |p: Vec2f| p.0 * n.0 + p.1 * n.1;
+    // The furthest either reaches along n, lowest first for a and highest for b
+    let extent = |s: Placed| match s {
+        Placed::Box(l, t, r, b) => {
+            let ds = [dot(Vec2f(l, t)), dot(Vec2f(r, t)), dot(Vec2f(l, b)), dot(Vec2f(r, b))];
+            (ds.iter().copied().fold(f32::INFINITY, f32::min), ds.iter().copied().fold(f32::NEG_INFINITY, f32::max))
+        }
+        Placed::Round(p0, p1, r) => (dot(p0).min(dot(p1)) - r, dot(p0).max(dot(p1)) + r),
+    };
+    extent(b).1 - extent(a).0
+}
+
+// The closest points between segments p0-p1 and q0-q1 (from Ericson's Real-Time Collision Detection)
+fn closest_points(p0: Vec2f, p1: Vec2f, q0: Vec2f, q1: Vec2f) -> (Vec2f, Vec2f) {
+    let dot = |a: (f32, f32), b: (f32, f32)| a.0 * b.0 + a.1 * b.1;
+    let d1 = (p1.0 - p0.0, p1.1 - p0.1);
+    let d2 = (q1.0 - q0.0, q1.1 - q0.1);
+    let r = (p0.0 - q0.0, p0.1 - q0.1);
+    let (a, e, f) = (dot(d1, d1), dot(d2, d2), dot(d2, r));
+    let unit = |x: f32| x.clamp(0.0, 1.0);
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if a <= f32::EPSILON {
+        (0.0, unit(f / e))
+    } else {
+        let c = dot(d1, r);
+        if e <= f32::EPSILON {
+            (unit(-c / a), 0.0)
+        } else {
+            let b = dot(d1, d2);
+            let denom = a * e - b * b;
+            let s = if denom > 0.0 { unit((b * f - c * e) / denom) } else { 0.0 };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                (unit(-c / a), 0.0)
+            } else if t > 1.0 {
+                (unit((b - c) / a), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (
+        Vec2f(p0.0 + d1.0 * s, p0.1 + d1.1 * s),
+        Vec2f(q0.0 + d2.0 * t, q0.1 + d2.1 * t),
+    )
+}
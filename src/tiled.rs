@@ -0,0 +1,871 @@
+//! Loading maps made with the Tiled editor (https://www.mapeditor.org), from either its
+//! XML (`.tmx`/`.tsx`) or JSON (`.tmj`/`.tsj`) formats.
+//!
+//! Each tile layer becomes its own `Tilemap`, tilesets (embedded or external) become `Tileset`s,
+//! and objects in object layers come back as `Spawn` points whose kind is the object's type (or class).
+//! Custom tile properties are mapped onto `Tile` fields by name, e.g. a bool property `solid`
+//! or a string property `slope` set to `up_right_45`; other int and string properties become tile tags.
+use crate::layers::LayeredTilemap;
+use crate::level::Spawn;
+use crate::texture::Texture;
+use crate::tiles::*;
+use crate::types::Vec2i;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// Tiled stores flip/rotation flags in the top four bits of each global tile ID.
+const GID_MASK: u32 = 0x0FFF_FFFF;
+
+/// Tiled times animation frames in milliseconds, but the engine counts 60 ticks per second.
+fn ms_to_ticks(ms: u32) -> usize {
+    ((ms as usize * 60 + 500) / 1000).max(1)
+}
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io(PathBuf, std::io::Error),
+    Image(PathBuf, image::ImageError),
+    Parse(PathBuf, String),
+    /// A feature of the file that the engine can't represent (yet)
+    Unsupported(String),
+    /// An object whose type doesn't name any entity kind
+    UnknownKind(String),
+}
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TiledError::Io(p, e) => write!(f, "couldn't read {}: {}", p.display(), e),
+            TiledError::Image(p, e) => write!(f, "couldn't load image {}: {}", p.display(), e),
+            TiledError::Parse(p, e) => write!(f, "couldn't parse {}: {}", p.display(), e),
+            TiledError::Unsupported(what) => write!(f, "unsupported Tiled feature: {}", what),
+            TiledError::UnknownKind(kind) => write!(f, "unknown object type {:?}", kind),
+        }
+    }
+}
+impl std::error::Error for TiledError {}
+
+/// A custom property value attached to a map, layer, or tile in Tiled
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+pub type Properties = HashMap<String, Property>;
+
+/// One tile layer of a Tiled map
+pub struct TiledLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub parallax: (f32, f32),
+    pub properties: Properties,
+    pub tilemap: Tilemap,
+}
+
+/// Everything we got out of a Tiled map file
+pub struct TiledMap {
+    /// Map size in tiles
+    pub dims: (usize, usize),
+    pub tile_size: (usize, usize),
+    pub properties: Properties,
+    pub tilesets: Vec<Rc<Tileset>>,
+    /// Tile layers in file order, i.e. back to front
+    pub layers: Vec<TiledLayer>,
+    /// Objects from every object layer, with their type as the kind
+    pub objects: Vec<Spawn<String>>,
+}
+
+impl TiledMap {
+    /// Load a `.tmx` or `.tmj` file; external tilesets and images are found relative to it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TiledError> {
+        let path = path.as_ref();
+        let raw = match path.extension().and_then(|e| e.to_str()) {
+            Some("tmj") | Some("json") => json::load_map(path)?,
+            _ => xml::load_map(path)?,
+        };
+        raw.build()
+    }
+    pub fn layer(&self, name: &str) -> Option<&TiledLayer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+    /// Stack the tile layers into one `LayeredTilemap`, which needs them all to be the same size with no offsets.
+    /// Layer z is the int property `z` if present, or else the layer's place in the file;
+    /// a layer collides unless it has the bool property `collides` set to false.
+    pub fn into_layered(self) -> Result<LayeredTilemap, TiledError> {
+        let mut layered = LayeredTilemap::new(Vec2i(0, 0), self.dims);
+        for (i, layer) in self.layers.into_iter().enumerate() {
+            if layer.tilemap.size() != self.dims || layer.tilemap.position != Vec2i(0, 0) {
+                return Err(TiledError::Unsupported(format!(
+                    "layer {:?} is offset or a different size from the map",
+                    layer.name
+                )));
+            }
+            let l = layered.add_tilemap(&layer.name, layer.tilemap);
+            l.visible = layer.visible;
+            l.opacity = layer.opacity;
+            l.parallax = layer.parallax;
+            if let Some(Property::Int(z)) = layer.properties.get("z") {
+                l.z = *z as i32;
+            } else {
+                l.z = i as i32;
+            }
+            l.collides = layer.properties.get("collides") != Some(&Property::Bool(false));
+        }
+        Ok(layered)
+    }
+    /// The object layers' contents converted to a game's entity type, e.g. `map.spawns::<EntityType>()`.
+    pub fn spawns<K: FromStr>(&self) -> Result<Vec<Spawn<K>>, TiledError> {
+        self.objects
+            .iter()
+            .map(|o| {
+                o.kind
+                    .parse()
+                    .map(|k| o.clone().map_kind(|_| k))
+                    .map_err(|_| TiledError::UnknownKind(o.kind.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Set the `Tile` field named by a custom property.
+/// Returns false if the property isn't one of `Tile`'s fields, in which case it may become a tag.
+fn apply_property(tile: &mut Tile, name: &str, value: &Property) -> bool {
+    let float = |v: &Property| match v {
+        Property::Float(f) => Some(*f as f32),
+        Property::Int(i) => Some(*i as f32),
+        _ => None,
+    };
+    match (name, value) {
+        ("solid", Property::Bool(b)) => tile.solid = *b,
+        ("one_way", Property::Bool(b)) => tile.one_way = *b,
+        ("hazard", Property::Bool(b)) => tile.hazard = *b,
+        ("damage", Property::Int(d)) => tile.damage = (*d).max(0).min(u16::MAX as i64) as u16,
+        ("friction", v) if float(v).is_some() => tile.friction = float(v).unwrap(),
+        ("speed", v) if float(v).is_some() => tile.speed = float(v).unwrap(),
+        ("slope", Property::Str(s)) => {
+            tile.slope = match s.as_str() {
+                "up_right_45" => Slope::UpRight45,
+                "up_left_45" => Slope::UpLeft45,
+                "up_right_22_low" => Slope::UpRight22Low,
+                "up_right_22_high" => Slope::UpRight22High,
+                "up_left_22_low" => Slope::UpLeft22Low,
+                "up_left_22_high" => Slope::UpLeft22High,
+                _ => Slope::None,
+            }
+        }
+        ("volume", Property::Str(s)) => {
+            tile.volume = match s.as_str() {
+                "ladder" => Volume::Ladder,
+                "water" => Volume::Water,
+                _ => Volume::None,
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+// Both file formats are read into these raw structures first, then turned into engine types.
+struct RawTileset {
+    firstgid: u32,
+    name: String,
+    tile_size: (usize, usize),
+    tilecount: usize,
+    /// How many tiles across the image is, if the file says
+    columns: Option<usize>,
+    margin: usize,
+    spacing: usize,
+    image: PathBuf,
+    tiles: Vec<RawTile>,
+}
+struct RawTile {
+    id: usize,
+    properties: Properties,
+    /// (tile id, milliseconds) pairs
+    animation: Vec<(usize, u32)>,
+}
+struct RawLayer {
+    name: String,
+    dims: (usize, usize),
+    look: Inherited,
+    properties: Properties,
+    gids: Vec<u32>,
+}
+/// What a group layer passes down to the layers inside it: they combine with each layer's own settings.
+#[derive(Clone, Copy)]
+struct Inherited {
+    offset: Vec2i,
+    visible: bool,
+    opacity: f32,
+    parallax: (f32, f32),
+}
+impl Inherited {
+    const ROOT: Inherited = Inherited {
+        offset: Vec2i(0, 0),
+        visible: true,
+        opacity: 1.0,
+        parallax: (1.0, 1.0),
+    };
+    fn child(self, offset: (f64, f64), visible: bool, opacity: f32, parallax: (f32, f32)) -> Self {
+        Inherited {
+            offset: Vec2i(
+                self.offset.0 + offset.0.round() as i32,
+                self.offset.1 + offset.1.round() as i32,
+            ),
+            visible: self.visible && visible,
+            opacity: self.opacity * opacity,
+            parallax: (self.parallax.0 * parallax.0, self.parallax.1 * parallax.1),
+        }
+    }
+}
+struct RawMap {
+    dims: (usize, usize),
+    tile_size: (usize, usize),
+    properties: Properties,
+    tilesets: Vec<RawTileset>,
+    layers: Vec<RawLayer>,
+    objects: Vec<Spawn<String>>,
+}
+
+impl RawTileset {
+    fn build(&self) -> Result<Tileset, TiledError> {
+        if self.tile_size.0 == 0 || self.tile_size.1 == 0 {
+            return Err(TiledError::Unsupported(format!("tileset {:?} has tiles with no size", self.name)));
+        }
+        let image = image::open(&self.image)
+            .map_err(|e| TiledError::Image(self.image.clone(), e))?
+            .into_rgba8();
+        if (image.width() as usize) < 2 * self.margin + self.tile_size.0 {
+            return Err(TiledError::Unsupported(format!(
+                "tileset {:?}'s image is too narrow for its tiles",
+                self.name
+            )));
+        }
+        // Tiles are found in the image by counting across and then down, so every tile has to fit in it
+        let fit = |len: u32, tile: usize| {
+            (len as usize + self.spacing).saturating_sub(2 * self.margin) / (tile + self.spacing)
+        };
+        let (columns, rows) = (fit(image.width(), self.tile_size.0), fit(image.height(), self.tile_size.1));
+        if let Some(c) = self.columns.filter(|&c| c != columns) {
+            return Err(TiledError::Unsupported(format!(
+                "tileset {:?} is {} tiles across, but its image is {}",
+                self.name, c, columns
+            )));
+        }
+        if self.tilecount > columns * rows {
+            return Err(TiledError::Unsupported(format!(
+                "tileset {:?} has {} tiles, but its image only holds {}",
+                self.name,
+                self.tilecount,
+                columns * rows
+            )));
+        }
+        let mut tiles = vec![Tile::OPEN; self.tilecount];
+        // Any other int or string properties become tile tags
+        let mut tags = vec![];
+        for t in self.tiles.iter() {
+            if let Some(tile) = tiles.get_mut(t.id) {
+                for (name, value) in t.properties.iter() {
+                    if apply_property(tile, name, value) {
+                        continue;
+                    }
+                    match value {
+                        Property::Int(i) => tags.push((t.id, name, TileTag::Int(*i))),
+                        Property::Str(s) => tags.push((t.id, name, TileTag::Str(s.clone()))),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        let count = tiles.len();
+        let grid = TileGrid::new(self.tile_size.0, self.tile_size.1)
+            .with_margin_spacing(self.margin, self.spacing);
+        let mut tileset = Tileset::with_grid(tiles, &Rc::new(Texture::new(image)), grid);
+        for (id, name, value) in tags {
+            tileset.set_tag(id, name, value);
+        }
+        for t in self.tiles.iter().filter(|t| !t.animation.is_empty()) {
+            if t.id >= count || t.animation.iter().any(|(f, _)| *f >= count) {
+                return Err(TiledError::Unsupported(format!(
+                    "tile {} in tileset {:?} animates with tiles it doesn't have",
+                    t.id, self.name
+                )));
+            }
+            tileset.add_animation(
+                t.id,
+                t.animation.iter().map(|(f, ms)| (*f, ms_to_ticks(*ms))).collect(),
+            );
+        }
+        Ok(tileset)
+    }
+}
+
+impl RawMap {
+    fn build(mut self) -> Result<TiledMap, TiledError> {
+        self.tilesets.sort_by_key(|ts| ts.firstgid);
+        let tilesets = self
+            .tilesets
+            .iter()
+            .map(|ts| ts.build().map(Rc::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut layers = vec![];
+        for layer in self.layers.iter() {
+            if layer.gids.len() != layer.dims.0 * layer.dims.1 {
+                return Err(TiledError::Unsupported(format!(
+                    "layer {:?} has {} tiles but is {}x{}",
+                    layer.name,
+                    layer.gids.len(),
+                    layer.dims.0,
+                    layer.dims.1
+                )));
+            }
+            // Our tilemaps draw from a single tileset, so every tile in a layer must come from the same one.
+            let mut which: Option<usize> = None;
+            let mut ids = Vec::with_capacity(layer.gids.len());
+            for &gid in layer.gids.iter() {
+                // Tilemaps can't draw tiles flipped or rotated, so don't pretend to
+                if gid & !GID_MASK != 0 {
+                    return Err(TiledError::Unsupported(format!(
+                        "layer {:?} has flipped or rotated tiles",
+                        layer.name
+                    )));
+                }
+                if gid == 0 {
+                    ids.push(None);
+                    continue;
+                }
+                let tsi = self
+                    .tilesets
+                    .iter()
+                    .rposition(|ts| ts.firstgid <= gid)
+                    .ok_or_else(|| {
+                        TiledError::Unsupported(format!("tile {} has no tileset", gid))
+                    })?;
+                match which {
+                    None => which = Some(tsi),
+                    Some(w) if w != tsi => {
+                        return Err(TiledError::Unsupported(format!(
+                            "layer {:?} mixes tiles from several tilesets",
+                            layer.name
+                        )))
+                    }
+                    _ => {}
+                }
+                let local = (gid - self.tilesets[tsi].firstgid) as usize;
+                if local >= tilesets[tsi].tiles.len() {
+                    return Err(TiledError::Unsupported(format!(
+                        "tile {} is outside its tileset {:?}",
+                        gid, self.tilesets[tsi].name
+                    )));
+                }
+                ids.push(Some(local));
+            }
+            let tileset = tilesets.get(which.unwrap_or(0)).ok_or_else(|| {
+                TiledError::Unsupported("a map with no tilesets".to_string())
+            })?;
+            // Tiled lets big tiles overhang their cells, but our tilemaps' cells are the size of their tiles.
+            if tileset.tile_size() != self.tile_size {
+                return Err(TiledError::Unsupported(format!(
+                    "layer {:?} uses tiles of a different size from the map's",
+                    layer.name
+                )));
+            }
+            layers.push(TiledLayer {
+                name: layer.name.clone(),
+                visible: layer.look.visible,
+                opacity: layer.look.opacity,
+                parallax: layer.look.parallax,
+                properties: layer.properties.clone(),
+                tilemap: Tilemap::from_ids(
+                    layer.look.offset,
+                    layer.dims,
+                    tileset,
+                    ids.into_iter()
+                        .map(|id| id.map_or(TileID::EMPTY, TileID::new))
+                        .collect(),
+                ),
+            });
+        }
+        Ok(TiledMap {
+            dims: self.dims,
+            tile_size: self.tile_size,
+            properties: self.properties,
+            tilesets,
+            layers,
+            objects: self.objects,
+        })
+    }
+}
+
+/// A Tiled object becomes a spawn point; tile objects are anchored at their bottom-left corner.
+fn object_spawn(
+    name: String,
+    kind: String,
+    (x, y): (f64, f64),
+    (w, h): (f64, f64),
+    is_tile: bool,
+) -> Spawn<String> {
+    let y = if is_tile { y - h } else { y };
+    Spawn {
+        kind,
+        position: Vec2i(x.round() as i32, y.round() as i32),
+        size: (w.max(0.0).round() as usize, h.max(0.0).round() as usize),
+        name,
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, TiledError> {
+    std::fs::read_to_string(path).map_err(|e| TiledError::Io(path.to_path_buf(), e))
+}
+
+fn relative_to(file: &Path, other: &str) -> PathBuf {
+    file.parent().unwrap_or_else(|| Path::new("")).join(other)
+}
+
+/// External tilesets may be in either format, whichever format the map itself is in.
+fn external_tileset(map_path: &Path, source: &str, firstgid: u32) -> Result<RawTileset, TiledError> {
+    let path = relative_to(map_path, source);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("tsj") | Some("json") => json::load_tileset(&path, firstgid),
+        _ => xml::load_tileset(&path, firstgid),
+    }
+}
+
+mod xml {
+    use super::*;
+    use roxmltree::{Document, Node};
+
+    fn parse_err(path: &Path, e: impl ToString) -> TiledError {
+        TiledError::Parse(path.to_path_buf(), e.to_string())
+    }
+    fn attr<T: FromStr>(path: &Path, node: Node, name: &str) -> Result<Option<T>, TiledError> {
+        match node.attribute(name) {
+            None => Ok(None),
+            Some(s) => s.trim().parse().map(Some).map_err(|_| {
+                parse_err(path, format!("bad {}={:?} on <{}>", name, s, node.tag_name().name()))
+            }),
+        }
+    }
+    fn req<T: FromStr>(path: &Path, node: Node, name: &str) -> Result<T, TiledError> {
+        attr(path, node, name)?.ok_or_else(|| {
+            parse_err(path, format!("<{}> is missing {}", node.tag_name().name(), name))
+        })
+    }
+    fn child<'a, 'i>(node: Node<'a, 'i>, tag: &str) -> Option<Node<'a, 'i>> {
+        node.children().find(|c| c.has_tag_name(tag))
+    }
+    fn properties(path: &Path, node: Node) -> Result<Properties, TiledError> {
+        let mut props = Properties::new();
+        if let Some(ps) = child(node, "properties") {
+            for p in ps.children().filter(|c| c.has_tag_name("property")) {
+                let name: String = req(path, p, "name")?;
+                let value = p
+                    .attribute("value")
+                    .or_else(|| p.text())
+                    .unwrap_or("")
+                    .to_string();
+                let value = match p.attribute("type").unwrap_or("string") {
+                    "bool" => Property::Bool(value == "true"),
+                    "int" | "object" => Property::Int(req(path, p, "value")?),
+                    "float" => Property::Float(req(path, p, "value")?),
+                    _ => Property::Str(value),
+                };
+                props.insert(name, value);
+            }
+        }
+        Ok(props)
+    }
+
+    /// Reads a `<tileset>` element, which is either embedded in the map or the root of a `.tsx` file at `path`.
+    fn tileset(path: &Path, node: Node, firstgid: u32) -> Result<RawTileset, TiledError> {
+        let image = child(node, "image")
+            .ok_or_else(|| TiledError::Unsupported("image collection tilesets".to_string()))?;
+        let mut tiles = vec![];
+        for t in node.children().filter(|c| c.has_tag_name("tile")) {
+            let mut animation = vec![];
+            if let Some(anim) = child(t, "animation") {
+                for f in anim.children().filter(|c| c.has_tag_name("frame")) {
+                    animation.push((req(path, f, "tileid")?, req(path, f, "duration")?));
+                }
+            }
+            tiles.push(RawTile {
+                id: req(path, t, "id")?,
+                properties: properties(path, t)?,
+                animation,
+            });
+        }
+        Ok(RawTileset {
+            firstgid,
+            name: attr(path, node, "name")?.unwrap_or_default(),
+            tile_size: (req(path, node, "tilewidth")?, req(path, node, "tileheight")?),
+            tilecount: req(path, node, "tilecount")?,
+            columns: attr(path, node, "columns")?,
+            margin: attr(path, node, "margin")?.unwrap_or(0),
+            spacing: attr(path, node, "spacing")?.unwrap_or(0),
+            image: relative_to(path, &req::<String>(path, image, "source")?),
+            tiles,
+        })
+    }
+    pub(super) fn load_tileset(path: &Path, firstgid: u32) -> Result<RawTileset, TiledError> {
+        let text = read_file(path)?;
+        let doc = Document::parse(&text).map_err(|e| parse_err(path, e))?;
+        tileset(path, doc.root_element(), firstgid)
+    }
+
+    fn layer_data(path: &Path, node: Node) -> Result<Vec<u32>, TiledError> {
+        let data = child(node, "data").ok_or_else(|| parse_err(path, "<layer> has no <data>"))?;
+        if child(data, "chunk").is_some() {
+            return Err(TiledError::Unsupported("infinite maps".to_string()));
+        }
+        match data.attribute("encoding") {
+            Some("csv") => data
+                .text()
+                .unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse().map_err(|_| parse_err(path, format!("bad tile {:?}", s))))
+                .collect(),
+            None => data
+                .children()
+                .filter(|c| c.has_tag_name("tile"))
+                .map(|t| attr(path, t, "gid").map(|g| g.unwrap_or(0)))
+                .collect(),
+            Some(other) => Err(TiledError::Unsupported(format!("{} layer data", other))),
+        }
+    }
+
+    /// Collect layers and objects from `node`'s children, descending into group layers.
+    fn layers(path: &Path, node: Node, parent: Inherited, map: &mut RawMap) -> Result<(), TiledError> {
+        for n in node.children().filter(|n| n.is_element()) {
+            let look = parent.child(
+                (
+                    attr(path, n, "offsetx")?.unwrap_or(0.0),
+                    attr(path, n, "offsety")?.unwrap_or(0.0),
+                ),
+                attr::<u8>(path, n, "visible")?.unwrap_or(1) != 0,
+                attr(path, n, "opacity")?.unwrap_or(1.0),
+                (
+                    attr(path, n, "parallaxx")?.unwrap_or(1.0),
+                    attr(path, n, "parallaxy")?.unwrap_or(1.0),
+                ),
+            );
+            let offset = look.offset;
+            match n.tag_name().name() {
+                "layer" => map.layers.push(RawLayer {
+                    name: attr(path, n, "name")?.unwrap_or_default(),
+                    dims: (req(path, n, "width")?, req(path, n, "height")?),
+                    look,
+                    properties: properties(path, n)?,
+                    gids: layer_data(path, n)?,
+                }),
+                "objectgroup" => {
+                    for o in n.children().filter(|c| c.has_tag_name("object")) {
+                        let kind = o.attribute("type").or_else(|| o.attribute("class"));
+                        let x: f64 = req(path, o, "x")?;
+                        let y: f64 = req(path, o, "y")?;
+                        map.objects.push(object_spawn(
+                            attr(path, o, "name")?.unwrap_or_default(),
+                            kind.unwrap_or("").to_string(),
+                            (x + offset.0 as f64, y + offset.1 as f64),
+                            (
+                                attr(path, o, "width")?.unwrap_or(0.0),
+                                attr(path, o, "height")?.unwrap_or(0.0),
+                            ),
+                            o.attribute("gid").is_some(),
+                        ));
+                    }
+                }
+                "group" => layers(path, n, look, map)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn load_map(path: &Path) -> Result<RawMap, TiledError> {
+        let text = read_file(path)?;
+        let doc = Document::parse(&text).map_err(|e| parse_err(path, e))?;
+        let root = doc.root_element();
+        if root.attribute("orientation").unwrap_or("orthogonal") != "orthogonal" {
+            return Err(TiledError::Unsupported("non-orthogonal maps".to_string()));
+        }
+        if attr::<u8>(path, root, "infinite")?.unwrap_or(0) != 0 {
+            return Err(TiledError::Unsupported("infinite maps".to_string()));
+        }
+        let mut map = RawMap {
+            dims: (req(path, root, "width")?, req(path, root, "height")?),
+            tile_size: (req(path, root, "tilewidth")?, req(path, root, "tileheight")?),
+            properties: properties(path, root)?,
+            tilesets: vec![],
+            layers: vec![],
+            objects: vec![],
+        };
+        for ts in root.children().filter(|c| c.has_tag_name("tileset")) {
+            let firstgid = req(path, ts, "firstgid")?;
+            map.tilesets.push(match ts.attribute("source") {
+                Some(source) => external_tileset(path, source, firstgid)?,
+                None => tileset(path, ts, firstgid)?,
+            });
+        }
+        layers(path, root, Inherited::ROOT, &mut map)?;
+        Ok(map)
+    }
+}
+
+mod json {
+    use super::*;
+    use serde_json::Value;
+
+    fn yes() -> bool {
+        true
+    }
+    fn one() -> f32 {
+        1.0
+    }
+
+    #[derive(Deserialize)]
+    struct JsonProperty {
+        name: String,
+        #[serde(rename = "type", default)]
+        kind: String,
+        value: Value,
+    }
+    #[derive(Deserialize)]
+    struct JsonTile {
+        id: usize,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+        #[serde(default)]
+        animation: Vec<JsonFrame>,
+    }
+    #[derive(Deserialize)]
+    struct JsonFrame {
+        tileid: usize,
+        duration: u32,
+    }
+    #[derive(Deserialize)]
+    struct JsonTileset {
+        #[serde(default)]
+        firstgid: u32,
+        source: Option<String>,
+        #[serde(default)]
+        name: String,
+        tilewidth: Option<usize>,
+        tileheight: Option<usize>,
+        tilecount: Option<usize>,
+        columns: Option<usize>,
+        image: Option<String>,
+        #[serde(default)]
+        margin: usize,
+        #[serde(default)]
+        spacing: usize,
+        #[serde(default)]
+        tiles: Vec<JsonTile>,
+    }
+    #[derive(Deserialize)]
+    struct JsonObject {
+        #[serde(default)]
+        name: String,
+        #[serde(rename = "type", default)]
+        kind: String,
+        #[serde(default)]
+        class: String,
+        x: f64,
+        y: f64,
+        #[serde(default)]
+        width: f64,
+        #[serde(default)]
+        height: f64,
+        gid: Option<u32>,
+    }
+    #[derive(Deserialize)]
+    struct JsonLayer {
+        #[serde(rename = "type")]
+        kind: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        width: usize,
+        #[serde(default)]
+        height: usize,
+        data: Option<Value>,
+        #[serde(default)]
+        chunks: Vec<Value>,
+        #[serde(default = "yes")]
+        visible: bool,
+        #[serde(default = "one")]
+        opacity: f32,
+        #[serde(default)]
+        offsetx: f64,
+        #[serde(default)]
+        offsety: f64,
+        #[serde(default = "one")]
+        parallaxx: f32,
+        #[serde(default = "one")]
+        parallaxy: f32,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+        #[serde(default)]
+        objects: Vec<JsonObject>,
+        #[serde(default)]
+        layers: Vec<JsonLayer>,
+    }
+    #[derive(Deserialize)]
+    struct JsonMap {
+        width: usize,
+        height: usize,
+        tilewidth: usize,
+        tileheight: usize,
+        #[serde(default)]
+        infinite: bool,
+        #[serde(default)]
+        orientation: Option<String>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>,
+        #[serde(default)]
+        tilesets: Vec<JsonTileset>,
+        #[serde(default)]
+        layers: Vec<JsonLayer>,
+    }
+
+    fn parse<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, TiledError> {
+        serde_json::from_str(&read_file(path)?)
+            .map_err(|e| TiledError::Parse(path.to_path_buf(), e.to_string()))
+    }
+    fn properties(props: &[JsonProperty]) -> Properties {
+        props
+            .iter()
+            .map(|p| {
+                let value = match (p.kind.as_str(), &p.value) {
+                    (_, Value::Bool(b)) => Property::Bool(*b),
+                    ("float", v) => Property::Float(v.as_f64().unwrap_or(0.0)),
+                    (_, Value::Number(n)) if n.is_i64() => Property::Int(n.as_i64().unwrap()),
+                    (_, Value::Number(n)) => Property::Float(n.as_f64().unwrap_or(0.0)),
+                    (_, Value::String(s)) => Property::Str(s.clone()),
+                    (_, v) => Property::Str(v.to_string()),
+                };
+                (p.name.clone(), value)
+            })
+            .collect()
+    }
+
+    fn tileset(path: &Path, ts: JsonTileset, firstgid: u32) -> Result<RawTileset, TiledError> {
+        let missing = |what: &str| TiledError::Parse(path.to_path_buf(), format!("tileset is missing {}", what));
+        Ok(RawTileset {
+            firstgid,
+            tile_size: (
+                ts.tilewidth.ok_or_else(|| missing("tilewidth"))?,
+                ts.tileheight.ok_or_else(|| missing("tileheight"))?,
+            ),
+            tilecount: ts.tilecount.ok_or_else(|| missing("tilecount"))?,
+            columns: ts.columns,
+            margin: ts.margin,
+            spacing: ts.spacing,
+            image: relative_to(
+                path,
+                ts.image
+                    .as_deref()
+                    .ok_or_else(|| TiledError::Unsupported("image collection tilesets".to_string()))?,
+            ),
+            tiles: ts
+                .tiles
+                .iter()
+                .map(|t| RawTile {
+                    id: t.id,
+                    properties: properties(&t.properties),
+                    animation: t.animation.iter().map(|f| (f.tileid, f.duration)).collect(),
+                })
+                .collect(),
+            name: ts.name,
+        })
+    }
+    pub(super) fn load_tileset(path: &Path, firstgid: u32) -> Result<RawTileset, TiledError> {
+        tileset(path, parse(path)?, firstgid)
+    }
+
+    fn layers(path: &Path, list: Vec<JsonLayer>, parent: Inherited, map: &mut RawMap) -> Result<(), TiledError> {
+        for l in list {
+            let look = parent.child(
+                (l.offsetx, l.offsety),
+                l.visible,
+                l.opacity,
+                (l.parallaxx, l.parallaxy),
+            );
+            let offset = look.offset;
+            match l.kind.as_str() {
+                "tilelayer" => {
+                    if !l.chunks.is_empty() {
+                        return Err(TiledError::Unsupported("infinite maps".to_string()));
+                    }
+                    let gids = match &l.data {
+                        Some(Value::Array(gids)) => gids
+                            .iter()
+                            .map(|g| g.as_u64().map(|g| g as u32))
+                            .collect::<Option<Vec<u32>>>()
+                            .ok_or_else(|| {
+                                TiledError::Parse(path.to_path_buf(), format!("bad tile data in {:?}", l.name))
+                            })?,
+                        _ => return Err(TiledError::Unsupported("base64 layer data".to_string())),
+                    };
+                    map.layers.push(RawLayer {
+                        dims: (l.width, l.height),
+                        look,
+                        properties: properties(&l.properties),
+                        gids,
+                        name: l.name,
+                    });
+                }
+                "objectgroup" => {
+                    for o in l.objects {
+                        let kind = if o.kind.is_empty() { o.class } else { o.kind };
+                        map.objects.push(object_spawn(
+                            o.name,
+                            kind,
+                            (o.x + offset.0 as f64, o.y + offset.1 as f64),
+                            (o.width, o.height),
+                            o.gid.is_some(),
+                        ));
+                    }
+                }
+                "group" => layers(path, l.layers, look, map)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn load_map(path: &Path) -> Result<RawMap, TiledError> {
+        let m: JsonMap = parse(path)?;
+        if m.orientation.as_deref().unwrap_or("orthogonal") != "orthogonal" {
+            return Err(TiledError::Unsupported("non-orthogonal maps".to_string()));
+        }
+        if m.infinite {
+            return Err(TiledError::Unsupported("infinite maps".to_string()));
+        }
+        let mut map = RawMap {
+            dims: (m.width, m.height),
+            tile_size: (m.tilewidth, m.tileheight),
+            properties: properties(&m.properties),
+            tilesets: vec![],
+            layers: vec![],
+            objects: vec![],
+        };
+        for ts in m.tilesets {
+            let firstgid = ts.firstgid;
+            map.tilesets.push(match &ts.source {
+                Some(source) => external_tileset(path, source, firstgid)?,
+                None => tileset(path, ts, firstgid)?,
+            });
+        }
+        layers(path, m.layers, Inherited::ROOT, &mut map)?;
+        Ok(map)
+    }
+}
@@ -2,27 +2,323 @@ use crate::graphics::Screen;
 use crate::texture::Texture;
 use crate::types::*;
 
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 
+/// The tile width and height used by `Tileset::new`
 pub const TILE_SZ: usize = 16;
 /// A graphical tile
-#[derive(Clone, Copy)]
+/// In data files, fields left out take their values from `Tile::OPEN`.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Tile {
-    pub solid: bool, // ... any extra data like collision flags or other properties
+    pub solid: bool,
+    /// Solid tiles with this set only block things landing on them from above
+    pub one_way: bool,
+    /// Solid tiles with a slope block along their sloped floor instead of their whole square
+    pub slope: Slope,
+    /// Touching this tile hurts (e.g. fire, spikes, lava)
+    pub hazard: bool,
+    /// How much it hurts, for games that keep track of health
+    pub damage: u16,
+    /// Scales how quickly things moving on this tile slow down; 1.0 is normal, ice is low
+    pub friction: f32,
+    /// Scales how fast things can move on this tile; 1.0 is normal, mud is low
+    pub speed: f32,
+    /// Areas you can be inside of rather than stand on, like ladders and water
+    pub volume: Volume,
+}
+impl Tile {
+    /// A plain tile that doesn't get in the way of anything
+    pub const OPEN: Tile = Tile {
+        solid: false,
+        one_way: false,
+        slope: Slope::None,
+        hazard: false,
+        damage: 0,
+        friction: 1.0,
+        speed: 1.0,
+        volume: Volume::None,
+    };
+    /// A plain wall
+    pub const SOLID: Tile = Tile {
+        solid: true,
+        ..Tile::OPEN
+    };
+}
+impl Default for Tile {
+    fn default() -> Self {
+        Tile::OPEN
+    }
+}
+/// The shape of a sloped tile's floor.
+/// The gentle (22.5 degree) slopes rise half a tile per tile, so each takes a Low tile followed by a High tile.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Slope {
+    None,
+    /// Rising from the bottom-left corner to the top-right corner
+    UpRight45,
+    /// Rising from the bottom-right corner to the top-left corner
+    UpLeft45,
+    UpRight22Low,
+    UpRight22High,
+    UpLeft22Low,
+    UpLeft22High,
+}
+impl Slope {
+    /// How far above the bottom of a w-by-h tile the floor is, x pixels from the tile's left edge.
+    /// None for tiles with no slope.
+    pub fn floor_height(self, x: i32, w: i32, h: i32) -> Option<i32> {
+        let x = x.max(0).min(w);
+        let (rise, base) = match self {
+            Slope::None => return None,
+            Slope::UpRight45 => (x * h / w, 0),
+            Slope::UpLeft45 => ((w - x) * h / w, 0),
+            Slope::UpRight22Low => (x * h / (2 * w), 0),
+            Slope::UpRight22High => (x * h / (2 * w), h / 2),
+            Slope::UpLeft22Low => ((w - x) * h / (2 * w), 0),
+            Slope::UpLeft22High => ((w - x) * h / (2 * w), h / 2),
+        };
+        Some(base + rise)
+    }
+}
+/// Places a thing can be inside of
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Volume {
+    None,
+    Ladder,
+    Water,
+}
+/// Extra data a game can hang on tiles, looked up by name
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TileTag {
+    Int(i64),
+    Str(String),
+}
+/// How the tiles of a tileset are laid out in its texture
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TileGrid {
+    pub tile_w: usize,
+    pub tile_h: usize,
+    /// Pixels between the edge of the texture and the first row and column of tiles
+    pub margin: usize,
+    /// Pixels between neighboring tiles
+    pub spacing: usize,
+}
+impl TileGrid {
+    pub fn new(tile_w: usize, tile_h: usize) -> Self {
+        Self {
+            tile_w,
+            tile_h,
+            margin: 0,
+            spacing: 0,
+        }
+    }
+    pub fn with_margin_spacing(self, margin: usize, spacing: usize) -> Self {
+        Self {
+            margin,
+            spacing,
+            ..self
+        }
+    }
+}
+impl Default for TileGrid {
+    fn default() -> Self {
+        Self::new(TILE_SZ, TILE_SZ)
+    }
 }
 /// A set of tiles used in multiple Tilemaps
 pub struct Tileset {
-    // Tile size is a constant, so we can find the tile in the texture using math
-    // (assuming the texture is a grid of tiles).
+    // The texture is a grid of tiles, so we can find each tile in it using math.
     pub tiles: Vec<Tile>,
     // Maybe a reference to a texture in a real program
     texture: Rc<Texture>,
     // In this design, each tileset is a distinct image.
     // Maybe not always the best choice if there aren't many tiles in a tileset!
+    grid: TileGrid,
+    // Tiles which cycle through other tiles' images, keyed by tile index
+    animations: HashMap<usize, TileAnimation>,
+    // Named tags on tiles, keyed by tile index
+    tags: HashMap<usize, HashMap<String, TileTag>>,
+    // Autotiling rules, keyed by terrain name
+    terrains: HashMap<String, Terrain>,
+}
+/// A looping sequence of tile images shown in place of one tile
+#[derive(Clone, Debug)]
+pub struct TileAnimation {
+    /// Which tile to show and for how many ticks
+    pub frames: Vec<(TileID, usize)>,
+    duration: usize,
+}
+impl TileAnimation {
+    pub fn duration(&self) -> usize {
+        self.duration
+    }
+    /// The tile to show at a given tick (the tick count since the game started is fine)
+    pub fn frame(&self, tick: usize) -> TileID {
+        let mut t = tick % self.duration;
+        for (id, dur) in self.frames.iter() {
+            if t < *dur {
+                return *id;
+            }
+            t -= dur;
+        }
+        unreachable!("tick is always within the animation's duration")
+    }
+}
+/// Which neighbours of a cell an autotiling terrain looks at
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Neighbours {
+    /// Edges only, using the `Terrain::N`, `E`, `S`, and `W` bits: 16 possible masks
+    Four,
+    /// Edges and corners, using all eight `Terrain` bits.
+    /// A corner only counts when both edges beside it do, which leaves 47 distinct masks.
+    Eight,
+}
+/// Autotiling rules for one kind of terrain (e.g. "water"): which tile to use for each neighbour bitmask.
+/// A neighbour's bit is set when it's the same terrain; cells off the edge of the map count as the same.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Terrain {
+    pub neighbours: Neighbours,
+    /// Neighbour bitmask to tile index
+    pub rules: BTreeMap<u8, usize>,
+    /// The tile to use when no rule matches, usually the plain middle tile
+    pub fallback: usize,
+}
+impl Terrain {
+    pub const N: u8 = 1;
+    pub const E: u8 = 2;
+    pub const S: u8 = 4;
+    pub const W: u8 = 8;
+    pub const NE: u8 = 16;
+    pub const SE: u8 = 32;
+    pub const SW: u8 = 64;
+    pub const NW: u8 = 128;
+    pub fn new(neighbours: Neighbours, fallback: usize) -> Self {
+        Self {
+            neighbours,
+            rules: BTreeMap::new(),
+            fallback,
+        }
+    }
+    /// Use tile `id` when the neighbours match `mask`
+    pub fn rule(mut self, mask: u8, id: usize) -> Self {
+        self.rules.insert(mask, id);
+        self
+    }
+    /// Which tile to use given the raw mask of same-terrain neighbours
+    pub fn pick(&self, mask: u8) -> TileID {
+        let edges = mask & (Self::N | Self::E | Self::S | Self::W);
+        let mask = match self.neighbours {
+            Neighbours::Four => edges,
+            Neighbours::Eight => {
+                let mut m = edges;
+                for &(corner, a, b) in &[
+                    (Self::NE, Self::N, Self::E),
+                    (Self::SE, Self::S, Self::E),
+                    (Self::SW, Self::S, Self::W),
+                    (Self::NW, Self::N, Self::W),
+                ] {
+                    if mask & corner != 0 && edges & a != 0 && edges & b != 0 {
+                        m |= corner;
+                    }
+                }
+                m
+            }
+        };
+        TileID(*self.rules.get(&mask).unwrap_or(&self.fallback))
+    }
+    /// Whether this terrain ever uses tile `id`
+    pub fn uses(&self, id: TileID) -> bool {
+        self.fallback == id.0 || self.rules.values().any(|t| *t == id.0)
+    }
 }
 /// Indices into a Tileset
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct TileID(usize);
+impl TileID {
+    /// A map cell with nothing in it: it is never drawn and never collides.
+    pub const EMPTY: TileID = TileID(usize::MAX);
+    /// The tile at index `idx` of a tileset; `Tilemap::set_tile` checks that the tileset has it.
+    pub fn new(idx: usize) -> Self {
+        TileID(idx)
+    }
+    pub fn index(self) -> usize {
+        self.0
+    }
+    pub fn is_empty(self) -> bool {
+        self == Self::EMPTY
+    }
+}
+/// A position measured in whole tiles from a tilemap's top-left corner.
+/// It can be negative or past the far edge, for positions off the map.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TileCoord(pub i32, pub i32);
+impl TileCoord {
+    /// The tile containing world position posn, on a grid of `tile_size` tiles whose top-left corner is at origin.
+    /// This rounds down, so a point just left of or above the grid gets -1 rather than 0.
+    pub fn from_world(posn: Vec2i, origin: Vec2i, (tw, th): (usize, usize)) -> Self {
+        TileCoord(
+            (posn.0 - origin.0).div_euclid(tw as i32),
+            (posn.1 - origin.1).div_euclid(th as i32),
+        )
+    }
+    /// The world position of this tile's top-left corner
+    pub fn to_world(self, origin: Vec2i, (tw, th): (usize, usize)) -> Vec2i {
+        Vec2i(origin.0 + self.0 * tw as i32, origin.1 + self.1 * th as i32)
+    }
+    /// The world-space pixel rectangle this tile covers
+    pub fn rect(self, origin: Vec2i, (tw, th): (usize, usize)) -> Rect {
+        let Vec2i(x, y) = self.to_world(origin, (tw, th));
+        Rect {
+            x,
+            y,
+            w: tw as u16,
+            h: th as u16,
+        }
+    }
+}
+/// A rectangle of cells in a tilemap, measured in tiles rather than pixels
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileRect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+impl TileRect {
+    pub fn new(x: usize, y: usize, w: usize, h: usize) -> Self {
+        Self { x, y, w, h }
+    }
+    pub fn contains(&self, other: TileRect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.w <= self.x + self.w
+            && other.y + other.h <= self.y + self.h
+    }
+}
+/// Why a tilemap edit was refused
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TileError {
+    /// The (x, y) tile coordinate is outside the map
+    OutOfBounds(usize, usize),
+    /// The tilemap's tileset has no tile with this ID
+    NoSuchTile(TileID),
+    /// The tilemap's tileset has no terrain with this name
+    NoSuchTerrain(String),
+}
+impl std::fmt::Display for TileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TileError::OutOfBounds(x, y) => write!(f, "tile ({}, {}) is outside the map", x, y),
+            TileError::NoSuchTile(id) => write!(f, "tileset has no tile {}", id.0),
+            TileError::NoSuchTerrain(name) => write!(f, "tileset has no terrain {:?}", name),
+        }
+    }
+}
+impl std::error::Error for TileError {}
 /// Grab a tile with a given ID
 impl std::ops::Index<TileID> for Tileset {
     type Output = Tile;
@@ -31,23 +327,116 @@ impl std::ops::Index<TileID> for Tileset {
     }
 }
 impl Tileset {
+    /// A tileset of TILE_SZ x TILE_SZ tiles packed edge to edge
     pub fn new(tiles: Vec<Tile>, texture: &Rc<Texture>) -> Self {
+        Self::with_grid(tiles, texture, TileGrid::default())
+    }
+    pub fn with_grid(tiles: Vec<Tile>, texture: &Rc<Texture>, grid: TileGrid) -> Self {
+        assert!(grid.tile_w > 0 && grid.tile_h > 0, "Tiles must have a size");
+        let (w, _h) = texture.size();
+        assert!(
+            w >= 2 * grid.margin + grid.tile_w,
+            "Texture is too narrow for a single tile"
+        );
         Self {
             tiles,
             texture: Rc::clone(texture),
+            grid,
+            animations: HashMap::new(),
+            tags: HashMap::new(),
+            terrains: HashMap::new(),
         }
     }
+    pub fn set_tag(&mut self, id: usize, name: &str, value: TileTag) {
+        assert!(self.contains(TileID(id)), "Tagging a nonexistent tile");
+        self.tags
+            .entry(id)
+            .or_default()
+            .insert(name.to_string(), value);
+    }
+    pub fn tag(&self, id: TileID, name: &str) -> Option<&TileTag> {
+        self.tags.get(&id.0).and_then(|t| t.get(name))
+    }
+    /// Every tile's tags, keyed by tile index
+    pub fn tags(&self) -> &HashMap<usize, HashMap<String, TileTag>> {
+        &self.tags
+    }
+    /// Every animated tile's animation, keyed by tile index
+    pub fn animations(&self) -> &HashMap<usize, TileAnimation> {
+        &self.animations
+    }
+    pub fn terrains(&self) -> &HashMap<String, Terrain> {
+        &self.terrains
+    }
+    /// Define a terrain that `Tilemap::paint_terrain` can paint with.
+    /// Terrains shouldn't share tiles, since a tile's terrain is worked out from its ID.
+    pub fn add_terrain(&mut self, name: &str, terrain: Terrain) {
+        assert!(
+            self.contains(TileID(terrain.fallback))
+                && terrain.rules.values().all(|t| self.contains(TileID(*t))),
+            "Terrain refers to nonexistent tiles"
+        );
+        self.terrains.insert(name.to_string(), terrain);
+    }
+    pub fn terrain(&self, name: &str) -> Option<&Terrain> {
+        self.terrains.get(name)
+    }
+    /// The name of the terrain tile `id` belongs to, if any
+    pub fn terrain_of(&self, id: TileID) -> Option<&str> {
+        self.terrains
+            .iter()
+            .find(|(_name, t)| t.uses(id))
+            .map(|(name, _t)| name.as_str())
+    }
+    /// Make tile `id` cycle through the given (tile index, ticks) frames whenever it's drawn.
+    /// Collision and tile properties still come from `id` itself.
+    pub fn add_animation(&mut self, id: usize, frames: Vec<(usize, usize)>) {
+        assert!(self.contains(TileID(id)), "Animating a nonexistent tile");
+        assert!(
+            frames.iter().all(|(f, _)| self.contains(TileID(*f))),
+            "Tile animation refers to nonexistent tiles"
+        );
+        let duration = frames.iter().map(|(_, d)| d).sum();
+        assert!(duration > 0, "Tile animation must last at least one tick");
+        self.animations.insert(
+            id,
+            TileAnimation {
+                frames: frames.into_iter().map(|(f, d)| (TileID(f), d)).collect(),
+                duration,
+            },
+        );
+    }
+    pub fn animation(&self, id: TileID) -> Option<&TileAnimation> {
+        self.animations.get(&id.0)
+    }
+    /// Which tile's image to draw for `id` at this tick
+    pub fn frame_at(&self, id: TileID, tick: usize) -> TileID {
+        self.animation(id).map_or(id, |a| a.frame(tick))
+    }
+    pub fn grid(&self) -> TileGrid {
+        self.grid
+    }
+    pub fn tile_size(&self) -> (usize, usize) {
+        (self.grid.tile_w, self.grid.tile_h)
+    }
     fn get_rect(&self, id: TileID) -> Rect {
         let idx = id.0;
+        let TileGrid {
+            tile_w,
+            tile_h,
+            margin,
+            spacing,
+        } = self.grid;
         let (w, _h) = self.texture.size();
-        let tw = w / TILE_SZ;
+        // How many tiles fit across: the last one in a row has no spacing after it
+        let tw = (w - 2 * margin + spacing) / (tile_w + spacing);
         let row = idx / tw;
         let col = idx - (row * tw);
         Rect {
-            x: col as i32 * TILE_SZ as i32,
-            y: row as i32 * TILE_SZ as i32,
-            w: TILE_SZ as u16,
-            h: TILE_SZ as u16,
+            x: (margin + col * (tile_w + spacing)) as i32,
+            y: (margin + row * (tile_h + spacing)) as i32,
+            w: tile_w as u16,
+            h: tile_h as u16,
         }
     }
     fn contains(&self, id: TileID) -> bool {
@@ -63,7 +452,9 @@ pub struct Tilemap {
     /// Which tileset is used for this tilemap
     tileset: Rc<Tileset>,
     /// A row-major grid of tile IDs in tileset
-    pub map: Vec<TileID>,
+    map: Vec<TileID>,
+    /// Regions changed since the last `take_dirty`
+    dirty: Vec<TileRect>,
 }
 impl Tilemap {
     pub fn new(
@@ -82,71 +473,310 @@ impl Tilemap {
             dims,
             tileset: Rc::clone(tileset),
             map: map.into_iter().map(TileID).collect(),
+            dirty: vec![],
+        }
+    }
+    /// Like `new`, but for maps built inside the engine (e.g. by importers), which may contain empty cells.
+    pub(crate) fn from_ids(
+        position: Vec2i,
+        dims: (usize, usize),
+        tileset: &Rc<Tileset>,
+        map: Vec<TileID>,
+    ) -> Self {
+        assert_eq!(dims.0 * dims.1, map.len(), "Tilemap is the wrong size!");
+        assert!(
+            map.iter().all(|tid| tid.is_empty() || tileset.contains(*tid)),
+            "Tilemap refers to nonexistent tiles"
+        );
+        Self {
+            position,
+            dims,
+            tileset: Rc::clone(tileset),
+            map,
+            dirty: vec![],
         }
     }
 
-    pub fn tile_id_at(&self, Vec2i(x, y): Vec2i) -> Option<(TileID,Rect)> {
-        // Translate into map coordinates
-        let x = (x - self.position.0) / TILE_SZ as i32;
-        let y = (y - self.position.1) / TILE_SZ as i32;
-        let rect_x = x*TILE_SZ as i32+self.position.0;
-        let rect_y = y*TILE_SZ as i32+self.position.1;
-
-        if y >= 0 && y < self.dims.1 as i32 &&  x >= 0 && x < self.dims.0 as i32{
-            Some((self.map[y as usize * self.dims.0 + x as usize],
-                Rect { x: rect_x, y: rect_y, w: TILE_SZ as u16, h: TILE_SZ as u16}))
-        }else{
+    pub fn tile_id_at(&self, posn: Vec2i) -> Option<(TileID,Rect)> {
+        let coord = self.tile_coord(posn);
+        self.index_of(coord)
+            .map(|idx| (self.map[idx], self.tile_rect(coord)))
+    }
+    /// The tile coordinate containing world position posn, even if it's off the map
+    pub fn tile_coord(&self, posn: Vec2i) -> TileCoord {
+        TileCoord::from_world(posn, self.position, self.tile_size())
+    }
+    /// The world-space pixel rectangle of the tile at coord
+    pub fn tile_rect(&self, coord: TileCoord) -> Rect {
+        coord.rect(self.position, self.tile_size())
+    }
+    /// Whether coord is on the map
+    pub fn in_bounds(&self, TileCoord(x, y): TileCoord) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.dims.0 && (y as usize) < self.dims.1
+    }
+    fn index_of(&self, coord: TileCoord) -> Option<usize> {
+        if self.in_bounds(coord) {
+            Some(coord.1 as usize * self.dims.0 + coord.0 as usize)
+        } else {
             None
         }
-
     }
-    
+
     pub fn size(&self) -> (usize, usize) {
         self.dims
     }
+    /// The width and height of one tile in pixels, which comes from the tileset
+    pub fn tile_size(&self) -> (usize, usize) {
+        self.tileset.tile_size()
+    }
     pub fn tile_at(&self, posn: Vec2i) -> Option<(Tile,Rect)> {
-        self.tile_id_at(posn).map(|(t,r)| (self.tileset[t], r))
+        self.tile_id_at(posn)
+            .filter(|(t, _r)| !t.is_empty())
+            .map(|(t, r)| (self.tileset[t], r))
+    }
+    /// Like `tile_at`, for a tile coordinate instead of a world position
+    pub fn tile_at_coord(&self, coord: TileCoord) -> Option<(Tile,Rect)> {
+        self.index_of(coord)
+            .map(|idx| self.map[idx])
+            .filter(|t| !t.is_empty())
+            .map(|t| (self.tileset[t], self.tile_rect(coord)))
+    }
+    pub fn tag_at(&self, posn: Vec2i, name: &str) -> Option<&TileTag> {
+        self.tile_id_at(posn)
+            .filter(|(t, _r)| !t.is_empty())
+            .and_then(|(t, _r)| self.tileset.tag(t, name))
+    }
+    pub fn tileset(&self) -> &Rc<Tileset> {
+        &self.tileset
+    }
+    /// The whole row-major grid of tile IDs
+    pub fn ids(&self) -> &[TileID] {
+        &self.map
+    }
+    /// The tile ID at tile coordinates (x, y)
+    pub fn get_tile(&self, x: usize, y: usize) -> Result<TileID, TileError> {
+        self.check_bounds(TileRect::new(x, y, 1, 1))?;
+        Ok(self.map[y * self.dims.0 + x])
+    }
+    /// Replace the tile at tile coordinates (x, y), returning the one that was there.
+    /// `TileID::EMPTY` clears the cell.
+    pub fn set_tile(&mut self, x: usize, y: usize, id: TileID) -> Result<TileID, TileError> {
+        self.check_bounds(TileRect::new(x, y, 1, 1))?;
+        self.check_id(id)?;
+        let old = std::mem::replace(&mut self.map[y * self.dims.0 + x], id);
+        if old != id {
+            self.mark_dirty(TileRect::new(x, y, 1, 1));
+        }
+        Ok(old)
+    }
+    /// Set every tile in `region` to `id`.
+    pub fn fill(&mut self, region: TileRect, id: TileID) -> Result<(), TileError> {
+        self.check_bounds(region)?;
+        self.check_id(id)?;
+        for y in region.y..region.y + region.h {
+            let row = y * self.dims.0;
+            for cell in self.map[row + region.x..row + region.x + region.w].iter_mut() {
+                *cell = id;
+            }
+        }
+        self.mark_dirty(region);
+        Ok(())
+    }
+    /// Copy the tiles in `from` so that its top-left corner lands on tile (x, y).
+    /// The regions may overlap.
+    pub fn copy_region(&mut self, from: TileRect, (x, y): (usize, usize)) -> Result<(), TileError> {
+        self.check_bounds(from)?;
+        let tiles: Vec<TileID> = (from.y..from.y + from.h)
+            .flat_map(|ty| {
+                let row = ty * self.dims.0;
+                self.map[row + from.x..row + from.x + from.w].iter().copied()
+            })
+            .collect();
+        self.paste(&tiles, TileRect::new(x, y, from.w, from.h))
+    }
+    /// Copy the tiles in `from` on another tilemap (e.g. a prefab room) so that its top-left corner lands on tile (x, y).
+    /// The other map's tile IDs must make sense in this map's tileset.
+    pub fn copy_from(
+        &mut self,
+        other: &Tilemap,
+        from: TileRect,
+        (x, y): (usize, usize),
+    ) -> Result<(), TileError> {
+        other.check_bounds(from)?;
+        let tiles: Vec<TileID> = (from.y..from.y + from.h)
+            .flat_map(|ty| {
+                let row = ty * other.dims.0;
+                other.map[row + from.x..row + from.x + from.w].iter().copied()
+            })
+            .collect();
+        if let Some(bad) = tiles.iter().find(|id| self.check_id(**id).is_err()) {
+            return Err(TileError::NoSuchTile(*bad));
+        }
+        self.paste(&tiles, TileRect::new(x, y, from.w, from.h))
+    }
+    /// Make tile (x, y) part of terrain `name`, then pick the right edge or corner tile for it and its neighbours.
+    pub fn paint_terrain(&mut self, x: usize, y: usize, name: &str) -> Result<(), TileError> {
+        let fallback = match self.tileset.terrain(name) {
+            Some(t) => t.fallback,
+            None => return Err(TileError::NoSuchTerrain(name.to_string())),
+        };
+        self.set_tile(x, y, TileID(fallback))?;
+        self.retile_around(x, y);
+        Ok(())
+    }
+    /// Like `set_tile`, but neighbouring terrain tiles are re-picked to match, e.g. when digging out a bit of cliff.
+    pub fn erase_terrain(&mut self, x: usize, y: usize, id: TileID) -> Result<(), TileError> {
+        self.set_tile(x, y, id)?;
+        self.retile_around(x, y);
+        Ok(())
+    }
+    /// Re-pick every terrain tile in `region` from its neighbours, e.g. after `fill` or loading a hand-made map.
+    pub fn retile(&mut self, region: TileRect) -> Result<(), TileError> {
+        self.check_bounds(region)?;
+        let tileset = Rc::clone(&self.tileset);
+        for y in region.y..region.y + region.h {
+            for x in region.x..region.x + region.w {
+                let id = self.map[y * self.dims.0 + x];
+                let name = match tileset.terrain_of(id) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let terrain = &tileset.terrains[name];
+                let same = |dx: i32, dy: i32| {
+                    let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                    if nx < 0 || ny < 0 || nx >= self.dims.0 as i32 || ny >= self.dims.1 as i32 {
+                        return true;
+                    }
+                    terrain.uses(self.map[ny as usize * self.dims.0 + nx as usize])
+                };
+                let mut mask = 0;
+                for &(bit, dx, dy) in &[
+                    (Terrain::N, 0, -1),
+                    (Terrain::E, 1, 0),
+                    (Terrain::S, 0, 1),
+                    (Terrain::W, -1, 0),
+                    (Terrain::NE, 1, -1),
+                    (Terrain::SE, 1, 1),
+                    (Terrain::SW, -1, 1),
+                    (Terrain::NW, -1, -1),
+                ] {
+                    if same(dx, dy) {
+                        mask |= bit;
+                    }
+                }
+                let pick = terrain.pick(mask);
+                if pick != id {
+                    self.map[y * self.dims.0 + x] = pick;
+                    self.mark_dirty(TileRect::new(x, y, 1, 1));
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Retile the 3x3 block around (x, y), clipped to the map
+    fn retile_around(&mut self, x: usize, y: usize) {
+        let (x0, y0) = (x.saturating_sub(1), y.saturating_sub(1));
+        let x1 = (x + 2).min(self.dims.0);
+        let y1 = (y + 2).min(self.dims.1);
+        self.retile(TileRect::new(x0, y0, x1 - x0, y1 - y0))
+            .expect("Region was clipped to the map");
+    }
+    /// Whether anything has changed since the last `take_dirty`
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+    /// The regions changed by edits since the last call, so cached drawings or collision data can be rebuilt.
+    pub fn take_dirty(&mut self) -> Vec<TileRect> {
+        std::mem::take(&mut self.dirty)
+    }
+    /// The world-space pixel rectangle covered by a region of tiles
+    pub fn region_rect(&self, region: TileRect) -> Rect {
+        let (tw, th) = self.tile_size();
+        let Vec2i(x, y) = TileCoord(region.x as i32, region.y as i32).to_world(self.position, (tw, th));
+        Rect {
+            x,
+            y,
+            w: (region.w * tw) as u16,
+            h: (region.h * th) as u16,
+        }
+    }
+    fn paste(&mut self, tiles: &[TileID], to: TileRect) -> Result<(), TileError> {
+        self.check_bounds(to)?;
+        for (ty, src) in (to.y..to.y + to.h).zip(tiles.chunks_exact(to.w.max(1))) {
+            let row = ty * self.dims.0;
+            self.map[row + to.x..row + to.x + to.w].copy_from_slice(src);
+        }
+        self.mark_dirty(to);
+        Ok(())
+    }
+    fn check_bounds(&self, r: TileRect) -> Result<(), TileError> {
+        let past = |start: usize, len: usize, end: usize| start.checked_add(len).map_or(true, |e| e > end);
+        if past(r.x, r.w, self.dims.0) || past(r.y, r.h, self.dims.1) {
+            // The far corner is always one of the cells that falls off the map
+            Err(TileError::OutOfBounds(
+                r.x.saturating_add(r.w.saturating_sub(1)),
+                r.y.saturating_add(r.h.saturating_sub(1)),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+    fn check_id(&self, id: TileID) -> Result<(), TileError> {
+        if id.is_empty() || self.tileset.contains(id) {
+            Ok(())
+        } else {
+            Err(TileError::NoSuchTile(id))
+        }
+    }
+    fn mark_dirty(&mut self, region: TileRect) {
+        if region.w == 0 || region.h == 0 || self.dirty.iter().any(|d| d.contains(region)) {
+            return;
+        }
+        self.dirty.retain(|d| !region.contains(*d));
+        self.dirty.push(region);
     }
     // ...
     /// Draws the portion of self appearing within screen.
     /// This could just as well be an extension trait on Screen defined in =tiles.rs= or something, like we did for =sprite.rs= and =draw_sprite=.
-    pub fn draw(&self, screen: &mut Screen) {
+    /// `tick` is the current frame count, which picks the frame of animated tiles.
+    pub fn draw(&self, screen: &mut Screen, tick: usize) {
+        self.draw_alpha(screen, tick, 255);
+    }
+    /// Like `draw`, but every tile is faded by alpha (255 is opaque).
+    pub fn draw_alpha(&self, screen: &mut Screen, tick: usize, alpha: u8) {
         let Rect {
             x: sx,
             y: sy,
             w: sw,
             h: sh,
         } = screen.bounds();
+        let (tw, th) = self.tile_size();
         // We'll draw from the topmost/leftmost visible tile to the bottommost/rightmost visible tile.
         // The camera combined with out position and size tell us what's visible.
-        // leftmost tile: get camera.x into our frame of reference, then divide down to tile units
-        // Note that it's also forced inside of 0..self.size.0
-        let left = ((sx - self.position.0) / TILE_SZ as i32)
-            .max(0)
-            .min(self.dims.0 as i32) as usize;
-        // rightmost tile: same deal, but with screen.x + screen.w.
-        let right = ((sx + (sw + TILE_SZ as u16) as i32- self.position.0) / TILE_SZ as i32)
-            .max(0)
-            .min(self.dims.0 as i32) as usize;
-        // ditto top and bot
-        let top = ((sy - self.position.1) / TILE_SZ as i32)
-            .max(0)
-            .min(self.dims.1 as i32) as usize;
-        let bot = ((sy + (sh + TILE_SZ as u16) as i32 - self.position.1) / TILE_SZ as i32)
-            .max(0)
-            .min(self.dims.1 as i32) as usize;
+        // top-left tile: the tile under the camera's top-left pixel
+        // Note that it's also forced inside of 0..self.size
+        let TileCoord(left, top) = self.tile_coord(Vec2i(sx, sy));
+        let left = left.max(0).min(self.dims.0 as i32) as usize;
+        let top = top.max(0).min(self.dims.1 as i32) as usize;
+        // bottom-right tile: same deal, but with screen.x + screen.w, plus one since ranges are exclusive.
+        let TileCoord(right, bot) = self.tile_coord(Vec2i(sx + sw as i32 + tw as i32, sy + sh as i32 + th as i32));
+        let right = right.max(0).min(self.dims.0 as i32) as usize;
+        let bot = bot.max(0).min(self.dims.1 as i32) as usize;
         // Now draw the tiles we need to draw where we need to draw them.
         // Note that we're zipping up the row index (y) with a slice of the map grid containing the necessary rows so we can avoid making a bounds check for each tile.
         for (y, row) in (top..bot)
             .zip(self.map[(top * self.dims.0)..(bot * self.dims.0)].chunks_exact(self.dims.0))
         {
             // We are in tile coordinates at this point so we'll need to translate back to pixel units and world coordinates to draw.
-            let ypx = (y * TILE_SZ) as i32 + self.position.1;
+            let ypx = (y * th) as i32 + self.position.1;
             // Here we can iterate through the column index and the relevant slice of the row in parallel
             for (x, id) in (left..right).zip(row[left..right].iter()) {
-                let xpx = (x * TILE_SZ) as i32 + self.position.0;
-                let frame = self.tileset.get_rect(*id);
-                screen.bitblt(&self.tileset.texture, frame, Vec2i(xpx, ypx));
+                if id.is_empty() {
+                    continue;
+                }
+                let xpx = (x * tw) as i32 + self.position.0;
+                let frame = self.tileset.get_rect(self.tileset.frame_at(*id, tick));
+                screen.bitblt_alpha(&self.tileset.texture, frame, Vec2i(xpx, ypx), alpha);
             }
         }
     }
@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub struct Rect {
     pub x: i32,
@@ -9,12 +12,93 @@ impl Rect {
     pub fn new(x: i32, y: i32, w: u16, h: u16) -> Self {
         Self { x, y, w, h }
     }
+    /// Whether the point p is inside the rect (its right and bottom edges aren't)
+    pub fn contains(&self, Vec2i(px, py): Vec2i) -> bool {
+        px >= self.x && px < self.x + self.w as i32 && py >= self.y && py < self.y + self.h as i32
+    }
 }
-#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub struct Vec2i(pub i32, pub i32);
+impl Add for Vec2i {
+    type Output = Vec2i;
+    fn add(self, other: Vec2i) -> Vec2i {
+        Vec2i(self.0 + other.0, self.1 + other.1)
+    }
+}
+impl Sub for Vec2i {
+    type Output = Vec2i;
+    fn sub(self, other: Vec2i) -> Vec2i {
+        Vec2i(self.0 - other.0, self.1 - other.1)
+    }
+}
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
 pub struct Vec2f(pub f32, pub f32);
+impl Vec2f {
+    pub fn dot(self, other: Vec2f) -> f32 {
+        self.0 * other.0 + self.1 * other.1
+    }
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+    /// This vector scaled to length 1, or zero if it has no length
+    pub fn normalized(self) -> Vec2f {
+        let len = self.length();
+        if len > 0.0 {
+            self * (1.0 / len)
+        } else {
+            Vec2f(0.0, 0.0)
+        }
+    }
+    /// The nearest whole pixel, rounding halves up (even below zero) so things keep their spacing
+    pub fn to_vec2i(self) -> Vec2i {
+        Vec2i((self.0 + 0.5).floor() as i32, (self.1 + 0.5).floor() as i32)
+    }
+}
+impl From<Vec2i> for Vec2f {
+    fn from(Vec2i(x, y): Vec2i) -> Self {
+        Vec2f(x as f32, y as f32)
+    }
+}
+impl Add for Vec2f {
+    type Output = Vec2f;
+    fn add(self, other: Vec2f) -> Vec2f {
+        Vec2f(self.0 + other.0, self.1 + other.1)
+    }
+}
+impl Sub for Vec2f {
+    type Output = Vec2f;
+    fn sub(self, other: Vec2f) -> Vec2f {
+        Vec2f(self.0 - other.0, self.1 - other.1)
+    }
+}
+impl Mul<f32> for Vec2f {
+    type Output = Vec2f;
+    fn mul(self, k: f32) -> Vec2f {
+        Vec2f(self.0 * k, self.1 * k)
+    }
+}
+impl Neg for Vec2f {
+    type Output = Vec2f;
+    fn neg(self) -> Vec2f {
+        Vec2f(-self.0, -self.1)
+    }
+}
+impl AddAssign for Vec2f {
+    fn add_assign(&mut self, other: Vec2f) {
+        *self = *self + other;
+    }
+}
+impl SubAssign for Vec2f {
+    fn sub_assign(&mut self, other: Vec2f) {
+        *self = *self - other;
+    }
+}
+impl MulAssign<f32> for Vec2f {
+    fn mul_assign(&mut self, k: f32) {
+        *self = *self * k;
+    }
+}
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub struct Rgba(pub u8, pub u8, pub u8, pub u8);
 
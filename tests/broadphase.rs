@@ -0,0 +1,45 @@
+use engine2d::collision::*;
+use engine2d::shapes::Shape;
+use engine2d::types::*;
+
+// n entities of assorted sizes, shapes and layers scattered over a small area, from a repeatable LCG
+fn scene(n: usize) -> (Vec<Vec2i>, Vec<(usize, usize)>, Vec<Collider>) {
+    let mut seed = 0x2545_F491u32;
+    let mut next = |bound: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        ((seed >> 8) % bound) as i32
+    };
+    let mut positions = vec![];
+    let mut sizes = vec![];
+    let mut colliders = vec![];
+    for _ in 0..n {
+        positions.push(Vec2i(next(400) - 200, next(400) - 200));
+        sizes.push((4 + next(40) as usize, 4 + next(40) as usize));
+        let shape = [Shape::Rect, Shape::Circle, Shape::Capsule][next(3) as usize];
+        colliders.push(Collider { shape, trigger: next(4) == 0, ..Collider::new(1 << next(3), 1 | 1 << next(3)) });
+    }
+    (positions, sizes, colliders)
+}
+
+#[test]
+fn grid_and_brute_force_agree_exactly() {
+    let (positions, sizes, colliders) = scene(300);
+    let brute = gather_contacts_brute(&positions, &sizes, &colliders);
+    assert!(brute.len() > 100, "only {} contacts", brute.len());
+    for &cell in &[8, 16, 33, 64, 500] {
+        assert_eq!(gather_contacts_grid(&positions, &sizes, &colliders, cell), brute, "cell size {}", cell);
+    }
+    assert_eq!(gather_contacts(&positions, &sizes, &colliders), brute);
+}
+
+#[test]
+fn spatial_hash_pairs_are_sorted_and_unique() {
+    let (positions, sizes, _) = scene(200);
+    let mut grid = SpatialHash::new(24);
+    for (i, (p, s)) in positions.iter().zip(sizes.iter()).enumerate() {
+        grid.insert(i, Rect::new(p.0, p.1, s.0 as u16, s.1 as u16));
+    }
+    let pairs = grid.pairs();
+    assert!(pairs.windows(2).all(|w| w[0] < w[1]));
+    assert!(pairs.iter().all(|(a, b)| a < b));
+}
@@ -0,0 +1,84 @@
+mod common;
+use engine2d::chunks::*;
+use engine2d::tiles::*;
+use engine2d::types::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// 4x4 tile chunks, 64 pixels square: solid in even columns of chunks and hazards in odd ones.
+// Every chunk loaded is noted down in the returned list.
+fn world() -> (ChunkedTilemap, Rc<RefCell<Vec<ChunkCoord>>>) {
+    let loads = Rc::new(RefCell::new(vec![]));
+    let noted = Rc::clone(&loads);
+    let loader: ChunkLoader = Box::new(move |(cx, cy)| {
+        noted.borrow_mut().push((cx, cy));
+        vec![TileID::new(if cx.rem_euclid(2) == 0 { 1 } else { 3 }); 16]
+    });
+    (ChunkedTilemap::new(&common::tileset(), (4, 4), loader), loads)
+}
+
+#[test]
+fn tiles_are_found_on_both_sides_of_seams() {
+    let (mut chunks, _) = world();
+    assert!(chunks.tile_at(Vec2i(0, 0)).is_none());
+    chunks.update(Rect::new(0, 0, 64, 64));
+    let solid = |chunks: &ChunkedTilemap, x: i32, y: i32| chunks.tile_at(Vec2i(x, y)).map(|(t, r)| (t.solid, r));
+    // Round the origin, where chunk coordinates go negative
+    assert_eq!(chunks.chunk_coord(Vec2i(-1, -1)), (-1, -1));
+    assert_eq!(solid(&chunks, -1, -1), Some((false, Rect::new(-16, -16, 16, 16))));
+    assert_eq!(solid(&chunks, 0, -1), Some((true, Rect::new(0, -16, 16, 16))));
+    assert_eq!(solid(&chunks, -1, 0), Some((false, Rect::new(-16, 0, 16, 16))));
+    assert_eq!(solid(&chunks, 0, 0), Some((true, Rect::new(0, 0, 16, 16))));
+    // Between chunks 0 and 1
+    assert_eq!(solid(&chunks, 63, 63), Some((true, Rect::new(48, 48, 16, 16))));
+    assert_eq!(solid(&chunks, 64, 63), Some((false, Rect::new(64, 48, 16, 16))));
+    assert_eq!(solid(&chunks, 63, 64), Some((true, Rect::new(48, 64, 16, 16))));
+    assert_eq!(chunks.chunk_coord(Vec2i(64, 64)), (1, 1));
+    assert_eq!(chunks.tile_id_at(Vec2i(64, 64)).map(|(id, _)| id), Some(TileID::new(3)));
+}
+
+#[test]
+fn chunks_load_near_the_camera_and_are_evicted_far_from_it() {
+    let (mut chunks, loads) = world();
+    assert!(chunks.update(Rect::new(0, 0, 64, 64)).is_empty());
+    let mut loaded: Vec<ChunkCoord> = chunks.loaded().map(|(c, _)| c).collect();
+    loaded.sort_unstable();
+    // The chunks within a chunk of (x, y), sorted
+    let around = |x: i32, y: i32| -> Vec<ChunkCoord> {
+        (x - 1..=x + 1).flat_map(|cx| (y - 1..=y + 1).map(move |cy| (cx, cy))).collect()
+    };
+    assert_eq!(loaded, around(0, 0));
+    assert_eq!(chunks.chunk((1, 1)).unwrap().ids()[0], TileID::new(3));
+
+    // One chunk over, only the new column loads, and nothing's far enough away to go
+    loads.borrow_mut().clear();
+    assert!(chunks.update(Rect::new(64, 0, 64, 64)).is_empty());
+    loads.borrow_mut().sort_unstable();
+    assert_eq!(*loads.borrow(), vec![(2, -1), (2, 0), (2, 1)]);
+    assert_eq!(chunks.loaded().count(), 12);
+
+    // Far away, everything from before is handed back
+    let mut evicted: Vec<ChunkCoord> = chunks.update(Rect::new(640, 0, 64, 64)).into_iter().map(|(c, _)| c).collect();
+    evicted.sort_unstable();
+    let mut before = around(0, 0);
+    before.extend(&[(2, -1), (2, 0), (2, 1)]);
+    before.sort_unstable();
+    assert_eq!(evicted, before);
+    assert!(chunks.tile_at(Vec2i(0, 0)).is_none());
+    assert_eq!(chunks.loaded().count(), 9);
+}
+
+#[test]
+fn edits_load_their_chunk_and_are_handed_back_on_eviction() {
+    let (mut chunks, loads) = world();
+    assert_eq!(chunks.set_tile(Vec2i(-1, 70), TileID::EMPTY), Ok(TileID::new(3)));
+    assert_eq!(*loads.borrow(), vec![(-1, 1)]);
+    assert!(chunks.tile_at(Vec2i(-1, 70)).is_none());
+    assert_eq!(chunks.set_tile(Vec2i(-1, 70), TileID::new(9)), Err(TileError::NoSuchTile(TileID::new(9))));
+    assert_eq!(chunks.load((-1, 1)).position, Vec2i(-64, 64));
+
+    let evicted = chunks.update(Rect::new(640, 640, 64, 64));
+    let (_, edited) = evicted.iter().find(|(c, _)| *c == (-1, 1)).unwrap();
+    assert_eq!(edited.tile_id_at(Vec2i(-1, 70)).map(|(id, _)| id), Some(TileID::EMPTY));
+    assert!(edited.is_dirty());
+}
@@ -0,0 +1,41 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+use engine2d::texture::Texture;
+use engine2d::tiles::*;
+use engine2d::types::*;
+use std::rc::Rc;
+
+/// 16x16 tiles: open, solid, a one-way platform and a hazard, in that order
+pub fn tileset() -> Rc<Tileset> {
+    let texture = Rc::new(Texture::new(image::RgbaImage::new(64, 16)));
+    Rc::new(Tileset::new(
+        vec![
+            Tile::OPEN,
+            Tile::SOLID,
+            Tile { one_way: true, ..Tile::SOLID },
+            Tile { hazard: true, ..Tile::OPEN },
+        ],
+        &texture,
+    ))
+}
+
+/// A map of `tileset`'s tiles drawn row by row: '#' is solid, '=' is a one-way platform, '~' is a hazard,
+/// and anything else is open
+pub fn map(rows: &[&str]) -> Tilemap {
+    map_at(Vec2i(0, 0), rows)
+}
+
+/// The same, with its top-left corner at position
+pub fn map_at(position: Vec2i, rows: &[&str]) -> Tilemap {
+    let tiles = rows
+        .iter()
+        .flat_map(|r| r.chars())
+        .map(|c| match c {
+            '#' => 1,
+            '=' => 2,
+            '~' => 3,
+            _ => 0,
+        })
+        .collect();
+    Tilemap::new(position, (rows[0].len(), rows.len()), &tileset(), tiles)
+}
@@ -0,0 +1,110 @@
+mod common;
+use common::map;
+use engine2d::controller::*;
+use engine2d::physics::Body;
+use engine2d::tiles::*;
+use engine2d::types::*;
+
+fn floor() -> Tilemap {
+    map(&[
+        "..............................",
+        "..............................",
+        "..............................",
+        "##############################",
+    ])
+}
+
+// Where a platformer body ends up after running right for a second from the left of the floor
+fn run_right(body: Body) -> Body {
+    let maps = [floor()];
+    let mut body = Body { position: Vec2f(0.0, 32.0), ..body };
+    let mut control = PlatformerController::new();
+    let input = PlatformerInput { right: true, ..PlatformerInput::default() };
+    for _ in 0..60 {
+        control.update(&mut body, (16, 16), &input, &maps);
+        assert!(control.grounded);
+    }
+    body
+}
+
+#[test]
+fn platformers_fall_onto_the_floor() {
+    let maps = [floor()];
+    let mut body = Body::new(Vec2i(16, 0));
+    let mut control = PlatformerController::new();
+    for _ in 0..60 {
+        control.update(&mut body, (16, 16), &PlatformerInput::default(), &maps);
+    }
+    assert_eq!(body.pixel(), Vec2i(16, 32));
+    assert!(control.grounded);
+    assert_eq!(body.velocity, Vec2f(0.0, 0.0));
+}
+
+#[test]
+fn platformers_honour_gravity_scale() {
+    let maps = [floor()];
+    let mut body = Body { gravity_scale: 0.0, ..Body::new(Vec2i(16, 0)) };
+    let mut control = PlatformerController::new();
+    for _ in 0..60 {
+        control.update(&mut body, (16, 16), &PlatformerInput::default(), &maps);
+    }
+    assert_eq!(body.pixel(), Vec2i(16, 0));
+    assert!(!control.grounded);
+}
+
+#[test]
+fn platformers_honour_drag_friction_and_max_speed() {
+    let free = run_right(Body::new(Vec2i(0, 0)));
+    assert_eq!(free.velocity.0, PlatformerController::new().run_speed);
+    for slowed in [
+        Body { drag: 5.0, ..Body::new(Vec2i(0, 0)) },
+        Body { friction: 600.0, ..Body::new(Vec2i(0, 0)) },
+        Body { max_speed: Vec2f(30.0, f32::INFINITY), ..Body::new(Vec2i(0, 0)) },
+    ]
+    .iter()
+    {
+        let body = run_right(*slowed);
+        assert!(body.position.0 < free.position.0 - 4.0, "{:?} ran as far as {:?}", slowed, free);
+    }
+    assert!(run_right(Body { max_speed: Vec2f(30.0, f32::INFINITY), ..Body::new(Vec2i(0, 0)) }).position.0 <= 30.0);
+}
+
+// Where a top-down body ends up after walking right for a second across an open map
+fn walk_right(body: Body) -> Body {
+    let maps = [map(&["..............................", ".............................."])];
+    let mut body = body;
+    let mut control = TopDownController::new(90.0);
+    for _ in 0..60 {
+        control.update(&mut body, (16, 16), Vec2f(1.0, 0.0), &maps);
+    }
+    body
+}
+
+#[test]
+fn top_down_walking_stops_at_walls() {
+    let maps = [map(&["....#", "....."])];
+    let mut body = Body::new(Vec2i(0, 0));
+    let mut control = TopDownController::new(90.0);
+    for _ in 0..60 {
+        control.update(&mut body, (16, 16), Vec2f(1.0, 0.0), &maps);
+    }
+    assert_eq!(body.pixel(), Vec2i(48, 0));
+    assert_eq!(control.facing, engine2d::sprite::Direction::Right);
+}
+
+#[test]
+fn top_down_walking_honours_drag_friction_and_max_speed() {
+    // Gravity doesn't pull sideways-on bodies down the screen
+    let free = walk_right(Body::new(Vec2i(0, 0)));
+    assert_eq!(free.pixel().1, 0);
+    for slowed in [
+        Body { drag: 5.0, ..Body::new(Vec2i(0, 0)) },
+        Body { friction: 600.0, ..Body::new(Vec2i(0, 0)) },
+        Body { max_speed: Vec2f(30.0, f32::INFINITY), ..Body::new(Vec2i(0, 0)) },
+    ]
+    .iter()
+    {
+        let body = walk_right(*slowed);
+        assert!(body.position.0 < free.position.0 - 4.0, "{:?} walked as far as {:?}", slowed, free);
+    }
+}
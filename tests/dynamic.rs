@@ -0,0 +1,59 @@
+use engine2d::collision::*;
+use engine2d::types::*;
+
+// Push apart any of these solid boxes that overlap, returning where they end up
+fn settle(mut positions: Vec<Vec2i>, sizes: &[(usize, usize)], masses: &[Mass]) -> Vec<Vec2i> {
+    let colliders = vec![Collider::default(); positions.len()];
+    let contacts = gather_contacts(&positions, sizes, &colliders);
+    restitute_dynamic(&mut positions, sizes, masses, &colliders, &contacts);
+    positions
+}
+
+#[test]
+fn pushes_are_split_by_mass() {
+    let sizes = [(16, 16), (16, 16)];
+    // Four pixels deep, so equal masses move two each
+    let equal = settle(vec![Vec2i(0, 0), Vec2i(12, 0)], &sizes, &[Mass::Dynamic(1.0), Mass::Dynamic(1.0)]);
+    assert_eq!(equal, vec![Vec2i(-2, 0), Vec2i(14, 0)]);
+    // Three times as heavy gets pushed a third as far
+    let uneven = settle(vec![Vec2i(0, 0), Vec2i(12, 0)], &sizes, &[Mass::Dynamic(1.0), Mass::Dynamic(3.0)]);
+    assert_eq!(uneven, vec![Vec2i(-3, 0), Vec2i(13, 0)]);
+    let uneven = settle(vec![Vec2i(0, 0), Vec2i(12, 0)], &sizes, &[Mass::Dynamic(3.0), Mass::Dynamic(1.0)]);
+    assert_eq!(uneven, vec![Vec2i(-1, 0), Vec2i(15, 0)]);
+    // Massless things still get pushed apart, half each
+    let massless = settle(vec![Vec2i(0, 0), Vec2i(12, 0)], &sizes, &[Mass::Dynamic(0.0), Mass::Dynamic(0.0)]);
+    assert_eq!(massless, vec![Vec2i(-2, 0), Vec2i(14, 0)]);
+}
+
+#[test]
+fn kinematic_entities_never_move() {
+    let sizes = [(64, 16), (16, 16)];
+    // However heavy the crate is, the platform carries on
+    let landed = settle(vec![Vec2i(0, 32), Vec2i(8, 20)], &sizes, &[Mass::Kinematic, Mass::Dynamic(1000.0)]);
+    assert_eq!(landed, vec![Vec2i(0, 32), Vec2i(8, 16)]);
+    let landed = settle(vec![Vec2i(8, 20), Vec2i(0, 32)], &[(16, 16), (64, 16)], &[Mass::Dynamic(1000.0), Mass::Kinematic]);
+    assert_eq!(landed, vec![Vec2i(8, 16), Vec2i(0, 32)]);
+    // Two kinematic entities pass right through each other
+    let both = vec![Vec2i(0, 32), Vec2i(8, 20)];
+    assert_eq!(settle(both.clone(), &sizes, &[Mass::Kinematic, Mass::Kinematic]), both);
+}
+
+#[test]
+fn stacks_on_kinematic_floors_settle_in_one_go() {
+    let sizes = [(64, 16), (16, 16), (16, 16)];
+    let masses = [Mass::Kinematic, Mass::Dynamic(1.0), Mass::Dynamic(1.0)];
+    // Each crate sunk four pixels into the thing below it
+    let stack = settle(vec![Vec2i(0, 32), Vec2i(0, 20), Vec2i(0, 8)], &sizes, &masses);
+    assert_eq!(stack, vec![Vec2i(0, 32), Vec2i(0, 16), Vec2i(0, 0)]);
+}
+
+#[test]
+fn triggers_are_not_pushed() {
+    let mut positions = vec![Vec2i(0, 0), Vec2i(12, 0)];
+    let sizes = [(16, 16), (16, 16)];
+    let colliders = vec![Collider::trigger(1, u32::MAX), Collider::default()];
+    let contacts = gather_contacts(&positions, &sizes, &colliders);
+    assert_eq!(contacts.len(), 1);
+    restitute_dynamic(&mut positions, &sizes, &[Mass::Dynamic(1.0), Mass::Dynamic(1.0)], &colliders, &contacts);
+    assert_eq!(positions, vec![Vec2i(0, 0), Vec2i(12, 0)]);
+}
@@ -0,0 +1,73 @@
+use engine2d::graphics::Screen;
+use engine2d::layers::*;
+use engine2d::texture::Texture;
+use engine2d::tiles::*;
+use engine2d::types::*;
+use std::rc::Rc;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+// Two 16x16 tiles: an open red one and a solid blue one
+fn tileset() -> Rc<Tileset> {
+    let mut image = image::RgbaImage::new(32, 16);
+    for (x, _y, px) in image.enumerate_pixels_mut() {
+        *px = image::Rgba(if x < 16 { RED } else { BLUE });
+    }
+    Rc::new(Tileset::new(vec![Tile::OPEN, Tile::SOLID], &Rc::new(Texture::new(image))))
+}
+
+// The colour of the top-left pixel of a one-tile screen after drawing whichever layers `draw` does
+fn top_left(layered: &LayeredTilemap, draw: impl Fn(&LayeredTilemap, &mut Screen)) -> [u8; 4] {
+    let mut fb = vec![0; 16 * 16 * 4];
+    draw(layered, &mut Screen::wrap(&mut fb, 16, 16, 4, Vec2i(0, 0)));
+    [fb[0], fb[1], fb[2], fb[3]]
+}
+
+#[test]
+fn layers_draw_in_z_order() {
+    let ts = tileset();
+    let mut layered = LayeredTilemap::new(Vec2i(0, 0), (1, 1));
+    layered.add_layer("sky", &ts, vec![1]).z = 5;
+    layered.add_layer("hills", &ts, vec![0]).z = -1;
+    // Added later but further back, so the sky still covers it
+    assert_eq!(top_left(&layered, |l, s| l.draw(s, 0)), BLUE);
+    assert_eq!(top_left(&layered, |l, s| l.draw_layers(s, 0, ..0)), RED);
+    assert_eq!(top_left(&layered, |l, s| l.draw_layers(s, 0, 0..)), BLUE);
+    // Ties go in the order the layers were added
+    layered.layer_mut("hills").unwrap().z = 5;
+    assert_eq!(top_left(&layered, |l, s| l.draw(s, 0)), RED);
+    layered.layer_mut("hills").unwrap().visible = false;
+    assert_eq!(top_left(&layered, |l, s| l.draw(s, 0)), BLUE);
+}
+
+#[test]
+fn only_colliding_layers_are_solid() {
+    let ts = tileset();
+    let mut layered = LayeredTilemap::new(Vec2i(32, 0), (2, 1));
+    layered.add_layer("ground", &ts, vec![1, 0]);
+    layered.add_layer("decor", &ts, vec![0, 1]).collides = false;
+    layered.add_layer("walls", &ts, vec![0, 0]);
+    let id = TileID::new;
+    let colliding: Vec<&[TileID]> = layered.colliding().map(|tm| tm.ids()).collect();
+    assert_eq!(colliding, vec![&[id(1), id(0)][..], &[id(0), id(0)][..]]);
+    assert_eq!(layered.solid_at(Vec2i(40, 8)), Some((Tile::SOLID, Rect::new(32, 0, 16, 16))));
+    assert_eq!(layered.solid_at(Vec2i(56, 8)), None);
+    layered.layer_mut("walls").unwrap().set_tile(1, 0, id(1)).unwrap();
+    assert_eq!(layered.solid_at(Vec2i(56, 8)), Some((Tile::SOLID, Rect::new(48, 0, 16, 16))));
+}
+
+#[test]
+fn edited_layers_stay_with_the_map() {
+    let ts = tileset();
+    let mut layered = LayeredTilemap::new(Vec2i(0, 0), (2, 2));
+    layered.add_layer("ground", &ts, vec![0; 4]);
+    let ground = layered.layer_mut("ground").unwrap();
+    assert_eq!(ground.set_tile(2, 0, TileID::new(1)), Err(TileError::OutOfBounds(2, 0)));
+    ground.fill(TileRect::new(0, 1, 2, 1), TileID::new(1)).unwrap();
+    assert_eq!(ground.take_dirty(), vec![TileRect::new(0, 1, 2, 1)]);
+    layered.set_position(Vec2i(100, 100));
+    let ground = layered.layer("ground").unwrap().tilemap();
+    assert_eq!(ground.position, Vec2i(100, 100));
+    assert_eq!(ground.tile_at(Vec2i(100, 116)), Some((Tile::SOLID, Rect::new(100, 116, 16, 16))));
+}
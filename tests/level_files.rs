@@ -0,0 +1,90 @@
+use engine2d::level::*;
+use engine2d::texture::Texture;
+use engine2d::tiles::*;
+use engine2d::types::*;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+// A fresh directory holding a 64x16 tileset image, for levels to be saved next to
+fn level_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("engine2d-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    image::RgbaImage::new(64, 16).save(dir.join("tiles.png")).unwrap();
+    dir
+}
+
+fn tileset() -> Tileset {
+    let texture = Rc::new(Texture::new(image::RgbaImage::new(64, 16)));
+    let mut tileset = Tileset::new(
+        vec![Tile::OPEN, Tile::SOLID, Tile { hazard: true, damage: 2, ..Tile::OPEN }, Tile::OPEN],
+        &texture,
+    );
+    tileset.add_animation(2, vec![(2, 10), (3, 5)]);
+    tileset.set_tag(1, "sound", TileTag::Str("stone".to_string()));
+    tileset
+}
+
+#[test]
+fn save_and_load_round_trips() {
+    let dir = level_dir("round-trip");
+    let tileset = Rc::new(tileset());
+    let map = Tilemap::new(Vec2i(-32, 16), (3, 2), &tileset, vec![1, 1, 2, 0, 3, 2]);
+    let mut file: LevelFile<String> = LevelFile::new();
+    let ts = file.add_tileset("tiles.png", &tileset);
+    file.add_tilemap(&map, ts);
+    file.spawns.push(Spawn::new("Player".to_string(), Vec2i(16, 0)));
+    file.save(dir.join("level.json")).unwrap();
+
+    let level = LevelFile::<String>::load(dir.join("level.json")).unwrap().build(&dir).unwrap();
+    assert_eq!(level.tilemaps.len(), 1);
+    let back = &level.tilemaps[0];
+    assert_eq!(back.position, map.position);
+    assert_eq!(back.size(), map.size());
+    assert_eq!(back.ids(), map.ids());
+    assert_eq!(level.tilesets[0].tiles, tileset.tiles);
+    assert_eq!(level.tilesets[0].grid(), tileset.grid());
+    assert_eq!(level.tilesets[0].animations().len(), 1);
+    assert_eq!(level.tilesets[0].animations()[&2].frames, tileset.animations()[&2].frames);
+    assert_eq!(level.tilesets[0].tags(), tileset.tags());
+    assert_eq!(level.spawns, vec![Spawn::new("Player".to_string(), Vec2i(16, 0))]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn newer_versions_are_refused() {
+    let dir = level_dir("version");
+    let mut file: LevelFile<String> = LevelFile::new();
+    file.version = LEVEL_FORMAT_VERSION + 1;
+    file.save(dir.join("level.json")).unwrap();
+    match LevelFile::<String>::load(dir.join("level.json")) {
+        Err(LevelError::Version(v)) => assert_eq!(v, LEVEL_FORMAT_VERSION + 1),
+        other => panic!("expected a version error, got {:?}", other.map(|_| ())),
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn bad_animations_are_errors() {
+    let dir = level_dir("animations");
+    for frames in &[vec![], vec![(1, 0), (2, 0)], vec![(9, 10)]] {
+        let mut file: LevelFile<String> = LevelFile::new();
+        let ts = file.add_tileset("tiles.png", &tileset());
+        file.tilesets[ts].animations.insert(1, frames.clone());
+        match file.build(&dir) {
+            Err(LevelError::Invalid(_)) => {}
+            other => panic!("expected {:?} to be invalid, got {:?}", frames, other.map(|_| ())),
+        }
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn game1_level_builds() {
+    let level = LevelFile::<String>::load("content/game1/level1.json")
+        .unwrap()
+        .build("content/game1")
+        .unwrap();
+    assert_eq!(level.tilemaps[0].size(), (20, 30));
+    assert_eq!(level.spawns[0].kind, "Player");
+    assert!(level.tilesets[0].tiles[3].hazard);
+}
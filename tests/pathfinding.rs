@@ -0,0 +1,236 @@
+mod common;
+use common::map;
+use engine2d::pathfinding::*;
+use engine2d::tiles::*;
+
+fn eight(corners: Corners) -> PathOptions {
+    PathOptions { connectivity: Connectivity::Eight, corners, ..PathOptions::default() }
+}
+
+// Every step of a path goes to a neighbouring tile
+fn assert_connected(path: &[TileCoord]) {
+    for w in path.windows(2) {
+        let (dx, dy) = (w[1].0 - w[0].0, w[1].1 - w[0].1);
+        assert!(dx.abs() <= 1 && dy.abs() <= 1 && (dx, dy) != (0, 0), "{:?} jumps", w);
+    }
+}
+
+#[test]
+fn start_is_goal() {
+    let m = map(&["...", "...", "..."]);
+    let path = find_path(&m, TileCoord(1, 1), TileCoord(1, 1), &PathOptions::default());
+    assert_eq!(path, Some(vec![TileCoord(1, 1)]));
+}
+
+#[test]
+fn goes_around_walls() {
+    let m = map(&[
+        ".....",
+        ".###.",
+        ".#...",
+        ".#.#.",
+        "...#.",
+    ]);
+    let path = find_path(&m, TileCoord(2, 2), TileCoord(0, 0), &PathOptions::default()).unwrap();
+    assert_eq!(path.first(), Some(&TileCoord(2, 2)));
+    assert_eq!(path.last(), Some(&TileCoord(0, 0)));
+    assert_connected(&path);
+    assert!(path.iter().all(|c| m.tile_at_coord(*c).map_or(true, |(t, _)| !t.solid)));
+    // Round the bottom left or the top right, it's 8 steps either way
+    assert_eq!(path.len(), 9);
+}
+
+#[test]
+fn unreachable_goals_have_no_path() {
+    let m = map(&[
+        "..#..",
+        "..#..",
+        "###..",
+        ".....",
+    ]);
+    let opts = PathOptions::default();
+    assert_eq!(find_path(&m, TileCoord(0, 0), TileCoord(4, 3), &opts), None);
+    // Walls and the area off the map can't be stood on either
+    assert_eq!(find_path(&m, TileCoord(3, 0), TileCoord(2, 0), &opts), None);
+    assert_eq!(find_path(&m, TileCoord(3, 0), TileCoord(5, 0), &opts), None);
+    assert_eq!(find_path(&m, TileCoord(3, 0), TileCoord(3, -1), &opts), None);
+}
+
+#[test]
+fn diagonals_respect_corners() {
+    // One blocked tile beside the diagonal step from (0, 0) to (1, 1)
+    let one = map(&["..", "#."]);
+    let (a, b) = (TileCoord(0, 0), TileCoord(1, 1));
+    assert_eq!(find_path(&one, a, b, &eight(Corners::Always)).unwrap().len(), 2);
+    assert_eq!(find_path(&one, a, b, &eight(Corners::IfOneOpen)).unwrap().len(), 2);
+    let never = find_path(&one, a, b, &eight(Corners::Never)).unwrap();
+    assert_eq!(never, vec![a, TileCoord(1, 0), b]);
+
+    // Both tiles beside it blocked: only squeezing between them gets through
+    let both = map(&[".#", "#."]);
+    assert_eq!(find_path(&both, a, b, &eight(Corners::Always)).unwrap().len(), 2);
+    assert_eq!(find_path(&both, a, b, &eight(Corners::IfOneOpen)), None);
+    assert_eq!(find_path(&both, a, b, &eight(Corners::Never)), None);
+}
+
+#[test]
+fn eight_way_paths_cut_across() {
+    let m = map(&["....", "....", "....", "...."]);
+    let (a, b) = (TileCoord(0, 0), TileCoord(3, 3));
+    assert_eq!(find_path(&m, a, b, &PathOptions::default()).unwrap().len(), 7);
+    let path = find_path(&m, a, b, &eight(Corners::Never)).unwrap();
+    assert_eq!(path, vec![a, TileCoord(1, 1), TileCoord(2, 2), b]);
+}
+
+#[test]
+fn costly_tiles_are_avoided_when_a_detour_is_cheaper() {
+    let m = map(&[
+        ".....",
+        "..~..",
+        ".....",
+    ]);
+    let hazard_cost = |cost: u32| move |t: Option<Tile>| Some(if t.map_or(false, |t| t.hazard) { cost } else { 1 });
+    let (a, b) = (TileCoord(0, 1), TileCoord(4, 1));
+    let opts = PathOptions::default();
+    // Going round costs two more steps, which beats a tile costing 10
+    let around = find_path_with(&m, a, b, &opts, hazard_cost(10)).unwrap();
+    assert_eq!(around.len(), 7);
+    assert!(!around.contains(&TileCoord(2, 1)));
+    // But a tile costing 2 is only one step's worth, so straight through is cheaper
+    let through = find_path_with(&m, a, b, &opts, hazard_cost(2)).unwrap();
+    assert_eq!(through.len(), 5);
+    assert!(through.contains(&TileCoord(2, 1)));
+    // And impassable hazards are never crossed
+    let never = find_path_with(&m, a, b, &opts, |t| if t.map_or(false, |t| t.hazard) { None } else { Some(1) }).unwrap();
+    assert!(!never.contains(&TileCoord(2, 1)));
+}
+
+#[test]
+fn big_entities_need_room() {
+    let m = map(&[
+        "......",
+        "......",
+        "...#..",
+        "......",
+    ]);
+    let opts = PathOptions { size: (2, 2), ..PathOptions::default() };
+    let path = find_path(&m, TileCoord(0, 0), TileCoord(4, 2), &opts).unwrap();
+    for c in path.iter() {
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+            assert_ne!(TileCoord(c.0 + dx, c.1 + dy), TileCoord(3, 2), "{:?} covers the wall", c);
+        }
+    }
+    // A 2x2 thing can't stand with its corner on the last column
+    assert_eq!(find_path(&m, TileCoord(0, 0), TileCoord(5, 0), &opts), None);
+}
+
+// Every tile's distance matches a field built from scratch, and its direction heads somewhere closer
+fn assert_matches_rebuild(field: &FlowField, m: &Tilemap, opts: &PathOptions) {
+    let fresh = FlowField::new(m, field.goal(), opts);
+    let (w, h) = m.size();
+    for y in 0..h as i32 {
+        for x in 0..w as i32 {
+            let c = TileCoord(x, y);
+            assert_eq!(field.distance(c), fresh.distance(c), "distance at {:?}", c);
+            match (field.distance(c), field.direction(c)) {
+                (Some(d), Some((dx, dy))) if c != field.goal() => {
+                    let next = field.distance(TileCoord(x + dx, y + dy)).expect("flows off the route");
+                    assert!(next < d, "{:?} flows away from the goal", c);
+                }
+                (None, dir) => assert_eq!(dir, None, "{:?} can't reach the goal", c),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn flow_field_updates_match_rebuilds() {
+    for opts in [
+        PathOptions::default(),
+        eight(Corners::Never),
+        eight(Corners::IfOneOpen),
+        PathOptions { size: (2, 2), ..eight(Corners::Never) },
+    ]
+    .iter()
+    {
+        let mut m = map(&[
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+        ]);
+        let mut field = FlowField::new(&m, TileCoord(1, 1), opts);
+        m.take_dirty();
+        // Put down and take away walls all over, a few at a time, in a repeatable order
+        let mut seed = 12345u32;
+        let mut next = |n: usize| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as usize % n
+        };
+        for _ in 0..40 {
+            for _ in 0..3 {
+                let (x, y) = (next(10), next(8));
+                if (x, y) != (1, 1) {
+                    m.set_tile(x, y, TileID::new(next(2))).unwrap();
+                }
+            }
+            if next(4) == 0 {
+                m.fill(TileRect::new(next(8), next(6), 2, 2), TileID::new(next(2))).unwrap();
+                m.set_tile(1, 1, TileID::new(0)).unwrap();
+            }
+            for region in m.take_dirty() {
+                field.update(&m, region);
+            }
+            assert_matches_rebuild(&field, &m, opts);
+        }
+    }
+}
+
+#[test]
+fn unreachable_tiles_have_no_direction() {
+    let m = map(&[
+        "..#..",
+        "..#..",
+        "..#..",
+    ]);
+    let field = FlowField::new(&m, TileCoord(0, 0), &PathOptions::default());
+    assert_eq!(field.distance(TileCoord(0, 0)), Some(0));
+    assert_eq!(field.direction(TileCoord(1, 0)), Some((-1, 0)));
+    assert_eq!(field.direction(TileCoord(0, 2)), Some((0, -1)));
+    assert_eq!(field.distance(TileCoord(4, 0)), None);
+    assert_eq!(field.direction(TileCoord(4, 0)), None);
+    assert_eq!(field.direction(TileCoord(2, 0)), None);
+}
+
+#[test]
+#[should_panic]
+fn flow_fields_need_a_footprint() {
+    let m = map(&["...", "..."]);
+    FlowField::new(&m, TileCoord(0, 0), &PathOptions { size: (0, 1), ..PathOptions::default() });
+}
+
+#[test]
+#[should_panic]
+fn paths_need_a_footprint() {
+    let m = map(&["...", "..."]);
+    find_path(&m, TileCoord(0, 0), TileCoord(2, 1), &PathOptions { size: (1, 0), ..PathOptions::default() });
+}
+
+#[test]
+fn huge_costs_do_not_wrap_around() {
+    let m = map(&[
+        ".....",
+        "..~..",
+        ".....",
+    ]);
+    let cost = |t: Option<Tile>| Some(if t.map_or(false, |t| t.hazard) { u32::MAX / 2 } else { 1 });
+    let (a, b) = (TileCoord(0, 1), TileCoord(4, 1));
+    let path = find_path_with(&m, a, b, &PathOptions::default(), cost).unwrap();
+    assert!(!path.contains(&TileCoord(2, 1)));
+    assert_eq!(path.len(), 7);
+}
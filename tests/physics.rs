@@ -0,0 +1,84 @@
+use engine2d::physics::*;
+use engine2d::types::*;
+
+const NO_GRAVITY: Vec2f = Vec2f(0.0, 0.0);
+
+// A floating body moving at velocity, with friction taking 10 pixels per second off it each half-second step
+fn sliding(velocity: Vec2f) -> Body {
+    Body { velocity, gravity_scale: 0.0, friction: 20.0, ..Body::new(Vec2i(0, 0)) }
+}
+
+#[test]
+fn friction_stops_bodies_without_reversing_them() {
+    let mut body = sliding(Vec2f(25.0, -25.0));
+    body.step(NO_GRAVITY, 0.5);
+    assert_eq!(body.velocity, Vec2f(15.0, -15.0));
+    body.step(NO_GRAVITY, 0.5);
+    body.step(NO_GRAVITY, 0.5);
+    // Only 5 was left, so it stops rather than going back the other way
+    assert_eq!(body.velocity, Vec2f(0.0, 0.0));
+    body.step(NO_GRAVITY, 0.5);
+    assert_eq!(body.velocity, Vec2f(0.0, 0.0));
+    for v in &[7.0, -7.0, 10.0, -10.0] {
+        let mut body = sliding(Vec2f(*v, 0.0));
+        body.step(NO_GRAVITY, 0.5);
+        assert_eq!(body.velocity, Vec2f(0.0, 0.0), "from {}", v);
+    }
+}
+
+#[test]
+fn friction_leaves_accelerating_axes_alone() {
+    let mut body = Body { acceleration: Vec2f(10.0, 0.0), ..sliding(Vec2f(20.0, 20.0)) };
+    body.step(NO_GRAVITY, 0.5);
+    assert_eq!(body.velocity, Vec2f(25.0, 10.0));
+    // Gravity counts as accelerating, so falling bodies aren't slowed
+    let mut body = Body { gravity_scale: 1.0, ..sliding(Vec2f(20.0, 0.0)) };
+    body.step(Vec2f(0.0, 100.0), 0.5);
+    assert_eq!(body.velocity, Vec2f(10.0, 50.0));
+}
+
+#[test]
+fn drag_and_max_speed_hold_bodies_back() {
+    let mut body = Body { drag: 1.0, gravity_scale: 0.0, ..Body::new(Vec2i(0, 0)) };
+    body.velocity = Vec2f(40.0, -40.0);
+    body.step(NO_GRAVITY, 0.5);
+    assert_eq!(body.velocity, Vec2f(20.0, -20.0));
+    assert_eq!(body.position, Vec2f(10.0, -10.0));
+    // Drag too strong for the step stops the body rather than flinging it backwards
+    let mut body = Body { drag: 4.0, ..body };
+    body.step(NO_GRAVITY, 0.5);
+    assert_eq!(body.velocity, Vec2f(0.0, 0.0));
+    let mut body = Body { max_speed: Vec2f(5.0, 1.0), gravity_scale: 0.0, ..Body::new(Vec2i(0, 0)) };
+    body.velocity = Vec2f(-40.0, 40.0);
+    body.step(NO_GRAVITY, 0.5);
+    assert_eq!(body.velocity, Vec2f(-5.0, 1.0));
+}
+
+#[test]
+fn integrate_writes_rounded_positions() {
+    let mut bodies = vec![Body { velocity: Vec2f(30.0, -90.0), gravity_scale: 0.0, ..Body::new(Vec2i(10, 10)) }];
+    let mut positions = vec![Vec2i(10, 10)];
+    integrate(&mut bodies, &mut positions, Vec2f(0.0, 500.0));
+    // Half a pixel right rounds up, and a pixel and a half up rounds down to one
+    assert_eq!(bodies[0].position, Vec2f(10.5, 8.5));
+    assert_eq!(positions, vec![Vec2i(11, 9)]);
+}
+
+#[test]
+fn pushes_against_the_motion_stop_bodies() {
+    let body = Body { position: Vec2f(10.25, 20.25), velocity: Vec2f(30.0, 120.0), ..Body::new(Vec2i(0, 0)) };
+    // Landing: pushed back up three pixels, so it stops falling but keeps running, sub-pixels and all
+    let mut bodies = vec![body];
+    sync_bodies(&mut bodies, &[Vec2i(10, 17)]);
+    assert_eq!(bodies[0].position, Vec2f(10.25, 17.25));
+    assert_eq!(bodies[0].velocity, Vec2f(30.0, 0.0));
+    // Hitting a wall on the right
+    let mut bodies = vec![body];
+    sync_bodies(&mut bodies, &[Vec2i(8, 20)]);
+    assert_eq!(bodies[0].velocity, Vec2f(0.0, 120.0));
+    // Pushed along the way it's going, or not at all, it keeps its speed
+    let mut bodies = vec![body, body];
+    sync_bodies(&mut bodies, &[Vec2i(12, 22), Vec2i(10, 20)]);
+    assert_eq!(bodies[0].position, Vec2f(12.25, 22.25));
+    assert_eq!((bodies[0].velocity, bodies[1]), (body.velocity, body));
+}
@@ -0,0 +1,123 @@
+mod common;
+use engine2d::collision::Collider;
+use engine2d::query::*;
+use engine2d::shapes::Shape;
+use engine2d::tiles::*;
+use engine2d::types::*;
+
+const TILES: u32 = 4;
+
+// Positions, sizes, colliders and tilemaps, to make a World from
+type Level = (Vec<Vec2i>, Vec<(usize, usize)>, Vec<Collider>, Vec<Tilemap>);
+
+// A box on layer 1, a circle on layer 2 and a capsule on layer 1 in a row,
+// above a map with one solid tile under the circle
+fn level() -> Level {
+    let positions = vec![Vec2i(32, 32), Vec2i(64, 32), Vec2i(96, 32)];
+    let sizes = vec![(16, 16), (16, 16), (32, 16)];
+    let colliders = vec![
+        Collider::new(1, u32::MAX),
+        Collider { shape: Shape::Circle, ..Collider::new(2, u32::MAX) },
+        Collider { shape: Shape::Capsule, ..Collider::new(1, u32::MAX) },
+    ];
+    let maps = vec![common::map(&[
+        "..........",
+        "..........",
+        "..........",
+        "..........",
+        "..........",
+        "....#.....",
+        "..........",
+        "..........",
+    ])];
+    (positions, sizes, colliders, maps)
+}
+
+#[test]
+fn points_find_what_is_under_them_on_the_mask_layers() {
+    let (positions, sizes, colliders, maps) = level();
+    let world = World::new(&positions, &sizes, &colliders, &maps, TILES);
+    assert_eq!(world.overlap_point(Vec2i(40, 40), 1), vec![Hit::Entity(0)]);
+    assert!(world.overlap_point(Vec2i(40, 40), 2).is_empty());
+    assert_eq!(world.overlap_point(Vec2i(72, 40), 2), vec![Hit::Entity(1)]);
+    // Inside the circle's box but outside the circle
+    assert!(world.overlap_point(Vec2i(64, 32), u32::MAX).is_empty());
+    assert_eq!(
+        world.overlap_point(Vec2i(70, 88), TILES),
+        vec![Hit::Tile { map: 0, coord: TileCoord(4, 5), tile: Tile::SOLID, rect: Rect::new(64, 80, 16, 16) }]
+    );
+    assert!(world.overlap_point(Vec2i(70, 88), 1 | 2).is_empty());
+}
+
+#[test]
+fn rects_just_touching_an_edge_find_nothing() {
+    let (positions, sizes, colliders, maps) = level();
+    let world = World::new(&positions, &sizes, &colliders, &maps, TILES);
+    assert!(world.overlap_rect(Rect::new(16, 32, 16, 16), u32::MAX).is_empty());
+    assert_eq!(world.overlap_rect(Rect::new(17, 32, 16, 16), u32::MAX), vec![Hit::Entity(0)]);
+    // Below the circle and above the tile, touching both
+    assert!(world.overlap_rect(Rect::new(64, 48, 16, 32), u32::MAX).is_empty());
+    assert_eq!(world.overlap_rect(Rect::new(64, 48, 16, 33), u32::MAX).len(), 1);
+    assert!(world.overlap_rect(Rect::new(40, 40, 0, 8), u32::MAX).is_empty());
+}
+
+#[test]
+fn rays_stop_at_the_nearest_hit() {
+    let (positions, sizes, colliders, maps) = level();
+    let world = World::new(&positions, &sizes, &colliders, &maps, TILES);
+    let hit = world.raycast(Vec2i(0, 40), Vec2i(200, 40), u32::MAX).unwrap();
+    assert_eq!((hit.hit, hit.point, hit.normal), (Hit::Entity(0), Vec2f(32.0, 40.0), Vec2f(-1.0, 0.0)));
+    assert_eq!(hit.t, 32.0 / 200.0);
+    let hit = world.raycast(Vec2i(0, 40), Vec2i(200, 40), 2).unwrap();
+    assert_eq!((hit.hit, hit.point, hit.normal), (Hit::Entity(1), Vec2f(64.0, 40.0), Vec2f(-1.0, 0.0)));
+    assert!(world.raycast(Vec2i(0, 40), Vec2i(200, 40), TILES).is_none());
+    assert!(world.raycast(Vec2i(0, 40), Vec2i(20, 40), u32::MAX).is_none());
+
+    // Going down the circle comes first, and coming up the tile does
+    let hit = world.raycast(Vec2i(72, 0), Vec2i(72, 120), u32::MAX).unwrap();
+    assert_eq!((hit.hit, hit.point, hit.normal), (Hit::Entity(1), Vec2f(72.0, 32.0), Vec2f(0.0, -1.0)));
+    let hit = world.raycast(Vec2i(72, 120), Vec2i(72, 0), u32::MAX).unwrap();
+    assert_eq!(hit.hit, Hit::Tile { map: 0, coord: TileCoord(4, 5), tile: Tile::SOLID, rect: Rect::new(64, 80, 16, 16) });
+    assert_eq!((hit.point, hit.normal), (Vec2f(72.0, 96.0), Vec2f(0.0, 1.0)));
+    assert!((hit.t - 0.2).abs() < 1e-6);
+}
+
+#[test]
+fn rays_starting_inside_hit_at_once() {
+    let (positions, sizes, colliders, maps) = level();
+    let world = World::new(&positions, &sizes, &colliders, &maps, TILES);
+    for (from, hit) in &[(Vec2i(40, 40), Hit::Entity(0)), (Vec2i(72, 40), Hit::Entity(1)), (Vec2i(110, 40), Hit::Entity(2))] {
+        let h = world.raycast(*from, Vec2i(0, 0), 1 | 2).unwrap();
+        assert_eq!((h.hit, h.point, h.normal, h.t), (*hit, Vec2f::from(*from), Vec2f(0.0, 0.0), 0.0));
+    }
+}
+
+#[test]
+fn rays_follow_the_round_ends_of_capsules() {
+    let (positions, sizes, colliders, maps) = level();
+    let world = World::new(&positions, &sizes, &colliders, &maps, TILES);
+    // Along the middle the flat side is hit; the ends are rounded
+    let hit = world.raycast(Vec2i(112, 0), Vec2i(112, 120), 1).unwrap();
+    assert_eq!((hit.hit, hit.point, hit.normal), (Hit::Entity(2), Vec2f(112.0, 32.0), Vec2f(0.0, -1.0)));
+    let hit = world.raycast(Vec2i(200, 40), Vec2i(0, 40), 1).unwrap();
+    assert_eq!((hit.hit, hit.point, hit.normal), (Hit::Entity(2), Vec2f(128.0, 40.0), Vec2f(1.0, 0.0)));
+    // Near the top the end cap bulges out past the flat middle, and is hit first
+    let hit = world.raycast(Vec2i(200, 33), Vec2i(0, 33), 1).unwrap();
+    assert_eq!(hit.hit, Hit::Entity(2));
+    assert!((hit.point.0 - (120.0 + 15.0f32.sqrt())).abs() < 1e-3, "{:?}", hit);
+    assert!(hit.normal.0 > 0.0 && hit.normal.1 < 0.0);
+    // Cutting across the corner of its box misses the rounded end
+    assert!(world.raycast(Vec2i(130, 30), Vec2i(126, 34), 1).is_none());
+}
+
+#[test]
+fn points_with_no_radius_never_give_nan() {
+    let positions = vec![Vec2i(50, 20)];
+    let sizes = vec![(0, 0)];
+    let colliders = vec![Collider { shape: Shape::Circle, ..Collider::new(1, u32::MAX) }];
+    let world = World::new(&positions, &sizes, &colliders, &[], TILES);
+    assert!(world.overlap_point(Vec2i(50, 20), 1).is_empty());
+    let hit = world.raycast(Vec2i(0, 20), Vec2i(100, 20), 1).unwrap();
+    assert_eq!((hit.t, hit.normal), (0.5, Vec2f(-1.0, 0.0)));
+    assert!(world.raycast(Vec2i(0, 21), Vec2i(100, 21), 1).is_none());
+}
@@ -0,0 +1,72 @@
+mod common;
+use engine2d::raycast::*;
+use engine2d::tiles::*;
+use engine2d::types::*;
+use std::f32::consts::FRAC_PI_4;
+
+// Solid tiles at (2, 1), covering pixels (32, 16) to (48, 32), and at (6, 3)
+fn map() -> Tilemap {
+    common::map(&[
+        "........",
+        "..#.....",
+        "........",
+        "......#.",
+    ])
+}
+
+fn hit(from: Vec2i, to: Vec2i) -> (TileCoord, Vec2f, (i32, i32), f32) {
+    let h = raycast(&map(), from, to).unwrap_or_else(|| panic!("{:?} to {:?} missed", from, to));
+    (h.coord, h.point, h.normal, h.t)
+}
+
+#[test]
+fn rays_going_up_and_left_hit_the_near_face() {
+    assert_eq!(hit(Vec2i(100, 24), Vec2i(0, 24)), (TileCoord(2, 1), Vec2f(48.0, 24.0), (1, 0), 0.52));
+    let (coord, point, normal, t) = hit(Vec2i(40, 60), Vec2i(40, 0));
+    assert_eq!((coord, point, normal), (TileCoord(2, 1), Vec2f(40.0, 32.0), (0, 1)));
+    assert!((t - 28.0 / 60.0).abs() < 1e-6);
+    // Up and to the left, crossing into the row above before reaching the tile's side
+    let (coord, point, normal, t) = hit(Vec2i(60, 40), Vec2i(20, 0));
+    assert_eq!((coord, point, normal), (TileCoord(2, 1), Vec2f(48.0, 28.0), (1, 0)));
+    assert!((t - 0.3).abs() < 1e-6);
+    // And the other way, from off the map
+    assert_eq!(hit(Vec2i(-20, 24), Vec2i(40, 24)), (TileCoord(2, 1), Vec2f(32.0, 24.0), (-1, 0), 52.0 / 60.0));
+}
+
+#[test]
+fn rays_starting_on_tile_edges() {
+    // Starting on the tile's right face and going into it is a hit straight away, on that face
+    assert_eq!(hit(Vec2i(48, 24), Vec2i(0, 24)), (TileCoord(2, 1), Vec2f(48.0, 24.0), (1, 0), 0.0));
+    // Going away from it is not
+    assert!(raycast(&map(), Vec2i(48, 24), Vec2i(90, 24)).is_none());
+    // Its left face is its own first column of pixels, so starting there is starting inside
+    assert_eq!(hit(Vec2i(32, 24), Vec2i(90, 24)), (TileCoord(2, 1), Vec2f(32.0, 24.0), (0, 0), 0.0));
+    // Starting on the line between two empty rows, going up
+    assert_eq!(hit(Vec2i(40, 48), Vec2i(40, 0)), (TileCoord(2, 1), Vec2f(40.0, 32.0), (0, 1), 1.0 / 3.0));
+    // Ending right on a face only touches it, which doesn't count; a pixel further does
+    assert!(raycast(&map(), Vec2i(96, 24), Vec2i(48, 24)).is_none());
+    assert_eq!(hit(Vec2i(96, 24), Vec2i(47, 24)), (TileCoord(2, 1), Vec2f(48.0, 24.0), (1, 0), 48.0 / 49.0));
+}
+
+#[test]
+fn vision_cones_need_range_angle_and_a_clear_line() {
+    let m = map();
+    let cone = VisionCone::new(Vec2f(1.0, 0.0), FRAC_PI_4, 100.0);
+    assert!(cone.sees(&m, Vec2i(8, 8), Vec2i(60, 8)));
+    assert!(cone.sees(&m, Vec2i(8, 8), Vec2i(8, 8)));
+    // Just inside and just outside 45 degrees
+    assert!(cone.sees(&m, Vec2i(56, 8), Vec2i(100, 46)));
+    assert!(!cone.sees(&m, Vec2i(56, 8), Vec2i(90, 46)));
+    assert!(!cone.sees(&m, Vec2i(8, 8), Vec2i(120, 8)));
+    // The tile at (2, 1) is in the way
+    assert!(!cone.sees(&m, Vec2i(8, 24), Vec2i(80, 24)));
+    // Facing doesn't have to be a unit vector, and can point backwards
+    let left = VisionCone::new(Vec2f(-5.0, 0.0), FRAC_PI_4, 100.0);
+    assert!(left.sees(&m, Vec2i(120, 24), Vec2i(56, 24)));
+    assert!(!left.sees(&m, Vec2i(120, 24), Vec2i(56, 24 + 70)));
+    assert!(!left.sees(&m, Vec2i(56, 24), Vec2i(120, 24)));
+    // Without a facing, only range and walls matter
+    let all_round = VisionCone::new(Vec2f(0.0, 0.0), 0.0, 100.0);
+    assert!(all_round.sees(&m, Vec2i(56, 24), Vec2i(120, 24)));
+    assert!(!all_round.sees(&m, Vec2i(56, 24), Vec2i(20, 24)));
+}
@@ -0,0 +1,79 @@
+mod common;
+use engine2d::collision::*;
+use engine2d::shapes::*;
+use engine2d::types::*;
+
+// Where a 16x16 circle puts its top-left corner to have its center at c
+fn circle_at(c: Vec2f) -> Vec2f {
+    Vec2f(c.0 - 8.0, c.1 - 8.0)
+}
+
+fn close(a: Option<(Vec2f, f32)>, b: (Vec2f, f32)) -> bool {
+    a.map_or(false, |(n, d)| (n.0 - b.0 .0).abs() < 1e-5 && (n.1 - b.0 .1).abs() < 1e-5 && (d - b.1).abs() < 1e-5)
+}
+
+#[test]
+fn circles_are_pushed_straight_off_box_sides() {
+    let overlap = |c: Vec2f| shape_overlap(Shape::Circle, circle_at(c), (16, 16), Shape::Rect, Vec2f(0.0, 0.0), (32, 16));
+    // Four pixels into the left side, and into the top
+    assert!(close(overlap(Vec2f(-4.0, 8.0)), (Vec2f(-1.0, 0.0), 4.0)));
+    assert!(close(overlap(Vec2f(16.0, -4.0)), (Vec2f(0.0, -1.0), 4.0)));
+    // With its center inside, out through the nearest side and a whole radius further
+    assert!(close(overlap(Vec2f(16.0, 3.0)), (Vec2f(0.0, -1.0), 11.0)));
+    assert!(close(overlap(Vec2f(29.0, 8.0)), (Vec2f(1.0, 0.0), 11.0)));
+    // Touching counts, with no depth
+    assert!(close(overlap(Vec2f(40.0, 8.0)), (Vec2f(1.0, 0.0), 0.0)));
+    // Pushing the other way round flips the normal
+    let flipped = shape_overlap(Shape::Rect, Vec2f(0.0, 0.0), (32, 16), Shape::Circle, circle_at(Vec2f(-4.0, 8.0)), (16, 16));
+    assert!(close(flipped, (Vec2f(1.0, 0.0), 4.0)));
+}
+
+#[test]
+fn circles_are_pushed_diagonally_off_box_corners() {
+    let overlap = |c: Vec2f| shape_overlap(Shape::Circle, circle_at(c), (16, 16), Shape::Rect, Vec2f(0.0, 0.0), (32, 16));
+    // Five pixels from the top-left corner, in a 3-4-5 triangle
+    assert!(close(overlap(Vec2f(-3.0, -4.0)), (Vec2f(-0.6, -0.8), 3.0)));
+    assert!(close(overlap(Vec2f(35.0, 20.0)), (Vec2f(0.6, 0.8), 3.0)));
+    // Their boxes overlap, but the round edge misses the corner
+    assert_eq!(overlap(Vec2f(-6.0, -6.0)), None);
+    assert!(shape_overlap(Shape::Rect, circle_at(Vec2f(-6.0, -6.0)), (16, 16), Shape::Rect, Vec2f(0.0, 0.0), (32, 16)).is_some());
+}
+
+#[test]
+fn capsules_are_boxes_with_round_ends() {
+    // A 32x16 capsule lying down, with the ends of its middle at x = 8 and 24
+    let overlap = |x: f32, y: f32| shape_overlap(Shape::Capsule, Vec2f(x, y), (32, 16), Shape::Rect, Vec2f(0.0, 16.0), (64, 16));
+    // Sunk two pixels into the floor along its flat bottom
+    assert!(close(overlap(16.0, 2.0), (Vec2f(0.0, -1.0), 2.0)));
+    // Hanging off the end, only the rounded part is over the corner
+    assert!(close(overlap(48.0, 2.0), (Vec2f(0.0, -1.0), 2.0)));
+    assert_eq!(overlap(62.0, 2.0), None);
+}
+
+// Let something fall four pixels a tick onto the right-hand corner of a lone solid tile, returning where it ends up
+fn drop_on_a_corner(shape: Shape) -> Vec2i {
+    let maps = [common::map(&["...", ".#.", "...", "...", "..."])];
+    let mut positions = vec![Vec2i(26, 0)];
+    let sizes = [(16, 16)];
+    let colliders = [Collider { shape, ..Collider::default() }];
+    for _ in 0..40 {
+        positions[0].1 += 4;
+        let mut contacts = gather_contacts_tilemap_shaped(&positions, &sizes, &colliders, &maps, u32::MAX);
+        restitute_shaped(&mut positions, &sizes, &colliders, &mut contacts);
+    }
+    positions[0]
+}
+
+#[test]
+fn circles_roll_off_corners_that_boxes_rest_on() {
+    assert_eq!(drop_on_a_corner(Shape::Rect), Vec2i(26, 0));
+    // The circle is pushed out and away from the corner until it falls past it
+    let rolled = drop_on_a_corner(Shape::Circle);
+    assert!(rolled.0 >= 32 && rolled.1 > 16, "{:?}", rolled);
+    // Because the corner's contact pushes out along the line from the corner, not straight up
+    let maps = [common::map(&["...", ".#.", "..."])];
+    let circle = [Collider { shape: Shape::Circle, ..Collider::default() }];
+    let contacts = gather_contacts_tilemap_shaped(&[Vec2i(28, 5)], &[(16, 16)], &circle, &maps, u32::MAX);
+    assert_eq!(contacts.len(), 1);
+    assert!(close(Some((contacts[0].normal, contacts[0].depth)), (Vec2f(0.8, -0.6), 3.0)));
+}
@@ -0,0 +1,111 @@
+mod common;
+use common::map;
+use engine2d::collision::*;
+use engine2d::types::*;
+
+#[test]
+fn sweeps_find_the_time_of_impact() {
+    let hit = sweep_rect(Rect::new(0, 0, 10, 10), Vec2i(20, 0), Rect::new(20, 0, 10, 10)).unwrap();
+    assert_eq!(hit.toi, 0.5);
+    assert_eq!(hit.normal, (-1, 0));
+    assert_eq!(hit.rect, Rect::new(20, 0, 10, 10));
+
+    let hit = sweep_rect(Rect::new(0, 40, 10, 10), Vec2i(0, -40), Rect::new(0, 0, 10, 10)).unwrap();
+    assert_eq!(hit.toi, 0.75);
+    assert_eq!(hit.normal, (0, 1));
+}
+
+#[test]
+fn sweeps_that_fall_short_or_miss_dont_hit() {
+    let target = Rect::new(20, 0, 10, 10);
+    assert_eq!(sweep_rect(Rect::new(0, 0, 10, 10), Vec2i(9, 0), target), None);
+    assert_eq!(sweep_rect(Rect::new(0, 0, 10, 10), Vec2i(-20, 0), target), None);
+    // Sliding along its edge isn't a hit
+    assert_eq!(sweep_rect(Rect::new(0, 10, 10, 10), Vec2i(40, 0), target), None);
+    // Ending up just touching is
+    assert_eq!(sweep_rect(Rect::new(0, 0, 10, 10), Vec2i(10, 0), target).unwrap().toi, 1.0);
+}
+
+#[test]
+fn zero_velocity_never_hits() {
+    let target = Rect::new(20, 0, 10, 10);
+    assert_eq!(sweep_rect(Rect::new(0, 0, 10, 10), Vec2i(0, 0), target), None);
+    assert_eq!(sweep_rect(Rect::new(10, 0, 10, 10), Vec2i(0, 0), target), None);
+    // Already overlapping doesn't count either
+    assert_eq!(sweep_rect(Rect::new(15, 0, 10, 10), Vec2i(0, 0), target), None);
+    assert_eq!(sweep_rect(Rect::new(15, 0, 10, 10), Vec2i(5, 0), target), None);
+}
+
+#[test]
+fn corners() {
+    let target = Rect::new(20, 20, 10, 10);
+    // Only brushing the corner on the way past isn't a hit
+    assert_eq!(sweep_rect(Rect::new(0, 20, 10, 10), Vec2i(20, -20), target), None);
+    // Running straight into it corner first is, and counts as landing on top
+    let hit = sweep_rect(Rect::new(0, 0, 10, 10), Vec2i(20, 20), target).unwrap();
+    assert_eq!(hit.toi, 0.5);
+    assert_eq!(hit.normal, (0, -1));
+    // Otherwise the face it crosses last is the one it hits
+    let hit = sweep_rect(Rect::new(0, 5, 10, 10), Vec2i(20, 20), target).unwrap();
+    assert_eq!(hit.toi, 0.5);
+    assert_eq!(hit.normal, (-1, 0));
+}
+
+#[test]
+fn tile_sweeps_hit_the_nearest_solid_tile() {
+    let m = map(&[
+        "....",
+        "..##",
+        "....",
+    ]);
+    let (hit, contact) = sweep_tilemap(Rect::new(0, 16, 16, 16), Vec2i(48, 0), &m).unwrap();
+    assert_eq!(hit.rect, Rect::new(32, 16, 16, 16));
+    assert_eq!(contact.rect, hit.rect);
+    assert!(contact.tile.solid);
+    assert_eq!(hit.toi, 16.0 / 48.0);
+    assert!(sweep_tilemap(Rect::new(0, 0, 16, 16), Vec2i(48, 0), &m).is_none());
+}
+
+#[test]
+fn one_way_tiles_only_stop_things_from_above() {
+    let m = map(&[
+        "....",
+        "..=.",
+        "....",
+    ]);
+    let (hit, _) = sweep_tilemap(Rect::new(32, 0, 16, 10), Vec2i(0, 10), &m).unwrap();
+    assert_eq!(hit.normal, (0, -1));
+    assert!(sweep_tilemap(Rect::new(32, 40, 16, 8), Vec2i(0, -20), &m).is_none());
+    assert!(sweep_tilemap(Rect::new(0, 16, 16, 16), Vec2i(40, 0), &m).is_none());
+}
+
+#[test]
+fn moving_slides_along_what_it_hits() {
+    let m = map(&[
+        "......",
+        "......",
+        "######",
+    ]);
+    // Falling diagonally onto the floor: stop on top of it and keep going sideways
+    let mut posn = Vec2i(0, 10);
+    let hits = move_and_slide(&mut posn, (16, 16), Vec2i(20, 20), &m);
+    assert_eq!(posn, Vec2i(20, 16));
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0.normal, (0, -1));
+
+    // Into a corner: both axes stop
+    let m = map(&[
+        "....#",
+        "....#",
+        "#####",
+    ]);
+    let mut posn = Vec2i(40, 10);
+    let hits = move_and_slide(&mut posn, (16, 16), Vec2i(20, 20), &m);
+    assert_eq!(posn, Vec2i(48, 16));
+    assert_eq!(hits.len(), 2);
+
+    // Nowhere to go: nothing moves and nothing's hit
+    let mut posn = Vec2i(5, 5);
+    assert!(move_and_slide(&mut posn, (16, 16), Vec2i(0, 0), &m).is_empty());
+    assert_eq!(posn, Vec2i(5, 5));
+}
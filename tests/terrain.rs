@@ -0,0 +1,104 @@
+use engine2d::texture::Texture;
+use engine2d::tiles::*;
+use engine2d::types::*;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+const N: u8 = Terrain::N;
+const E: u8 = Terrain::E;
+const S: u8 = Terrain::S;
+const W: u8 = Terrain::W;
+const SE: u8 = Terrain::SE;
+const SW: u8 = Terrain::SW;
+const NW: u8 = Terrain::NW;
+
+// A terrain with a tile for every mask, numbered by the mask itself, so `pick` shows which mask it settled on
+fn every_mask(neighbours: Neighbours) -> Terrain {
+    (0..=255).fold(Terrain::new(neighbours, 1000), |t, m| t.rule(m, m as usize))
+}
+
+#[test]
+fn blob_terrains_see_47_masks() {
+    let terrain = every_mask(Neighbours::Eight);
+    let picked: BTreeSet<usize> = (0..=255).map(|m| terrain.pick(m).index()).collect();
+    assert_eq!(picked.len(), 47);
+    // A corner only counts with both edges beside it
+    assert_eq!(terrain.pick(N | Terrain::NE).index(), N as usize);
+    assert_eq!(terrain.pick(N | E | Terrain::NE).index(), (N | E | Terrain::NE) as usize);
+    assert_eq!(terrain.pick(S | SE | SW).index(), S as usize);
+    assert_eq!(terrain.pick(255).index(), 255);
+
+    let terrain = every_mask(Neighbours::Four);
+    let picked: BTreeSet<usize> = (0..=255).map(|m| terrain.pick(m).index()).collect();
+    assert_eq!(picked.len(), 16);
+    assert_eq!(terrain.pick(255).index(), (N | E | S | W) as usize);
+}
+
+#[test]
+fn masks_without_rules_use_the_fallback() {
+    let terrain = Terrain::new(Neighbours::Eight, 4).rule(0, 5);
+    assert_eq!(terrain.pick(0), TileID::new(5));
+    assert_eq!(terrain.pick(N), TileID::new(4));
+    assert!(terrain.uses(TileID::new(4)) && terrain.uses(TileID::new(5)) && !terrain.uses(TileID::new(0)));
+}
+
+// 16 plain tiles, with water using tile 4 for the middle and a few more for edges and corners
+fn water_map(dims: (usize, usize)) -> Tilemap {
+    let texture = Rc::new(Texture::new(image::RgbaImage::new(64, 64)));
+    let mut tileset = Tileset::new(vec![Tile::OPEN; 16], &texture);
+    tileset.add_terrain(
+        "water",
+        Terrain::new(Neighbours::Eight, 4)
+            .rule(0, 5)
+            .rule(255, 8)
+            .rule(E | S | SE, 9)
+            .rule(E | S | W | SE | SW, 10)
+            .rule(N | W | NW, 11)
+            .rule(E | W, 12),
+    );
+    Tilemap::new(Vec2i(0, 0), dims, &Rc::new(tileset), vec![0; dims.0 * dims.1])
+}
+
+fn at(m: &Tilemap, x: usize, y: usize) -> usize {
+    m.get_tile(x, y).unwrap().index()
+}
+
+#[test]
+fn painting_picks_tiles_from_the_neighbours() {
+    let mut m = water_map((5, 5));
+    m.paint_terrain(2, 2, "water").unwrap();
+    assert_eq!(at(&m, 2, 2), 5);
+    // A 3x3 pond: its middle, a corner and an edge
+    for y in 1..4 {
+        for x in 1..4 {
+            m.paint_terrain(x, y, "water").unwrap();
+        }
+    }
+    assert_eq!((at(&m, 2, 2), at(&m, 1, 1), at(&m, 2, 1)), (8, 9, 10));
+    // Neither diagonal counts for the top middle once the middle's dug out
+    m.erase_terrain(2, 2, TileID::new(0)).unwrap();
+    assert_eq!((at(&m, 2, 2), at(&m, 2, 1), at(&m, 1, 1)), (0, 12, 4));
+    assert_eq!(m.paint_terrain(0, 0, "lava"), Err(TileError::NoSuchTerrain("lava".to_string())));
+}
+
+#[test]
+fn cells_off_the_map_count_as_the_same_terrain() {
+    let mut m = water_map((5, 5));
+    m.paint_terrain(0, 0, "water").unwrap();
+    assert_eq!(at(&m, 0, 0), 11);
+}
+
+#[test]
+fn retiling_a_fill_matches_painting_it() {
+    let mut painted = water_map((5, 5));
+    for y in 1..4 {
+        for x in 1..4 {
+            painted.paint_terrain(x, y, "water").unwrap();
+        }
+    }
+    let mut filled = water_map((5, 5));
+    filled.fill(TileRect::new(1, 1, 3, 3), TileID::new(4)).unwrap();
+    filled.retile(TileRect::new(0, 0, 5, 5)).unwrap();
+    assert_eq!(filled.ids(), painted.ids());
+    assert_eq!(filled.retile(TileRect::new(3, 3, 3, 3)), Err(TileError::OutOfBounds(5, 5)));
+}
@@ -0,0 +1,110 @@
+mod common;
+use engine2d::collision::*;
+use engine2d::tiles::*;
+use engine2d::types::*;
+
+const TILES: u32 = 2;
+
+// A 3x3 map with a solid tile in the middle
+fn map() -> Tilemap {
+    common::map(&["...", ".#.", "..."])
+}
+
+#[test]
+fn solid_colliders_get_pushed_out_of_tiles() {
+    let maps = [map()];
+    let mut positions = vec![Vec2i(10, 20)];
+    let sizes = vec![(8, 8)];
+    let colliders = vec![Collider::new(1, u32::MAX)];
+    let mut contacts = gather_contacts_tilemap_shaped(&positions, &sizes, &colliders, &maps, TILES);
+    assert_eq!(contacts.len(), 1);
+    assert!(!contacts[0].trigger);
+    restitute_shaped(&mut positions, &sizes, &colliders, &mut contacts);
+    assert_eq!(positions[0], Vec2i(8, 20));
+}
+
+#[test]
+fn triggers_are_left_where_they_are() {
+    let maps = [map()];
+    let mut positions = vec![Vec2i(10, 20)];
+    let sizes = vec![(8, 8)];
+    let colliders = vec![Collider::trigger(1, u32::MAX)];
+    let mut contacts = gather_contacts_tilemap_shaped(&positions, &sizes, &colliders, &maps, TILES);
+    assert_eq!(contacts.len(), 1);
+    assert!(contacts[0].trigger);
+    restitute_shaped(&mut positions, &sizes, &colliders, &mut contacts);
+    assert_eq!(positions[0], Vec2i(10, 20));
+}
+
+#[test]
+fn masks_without_the_tile_layer_pass_through_tiles() {
+    let maps = [map()];
+    let positions = vec![Vec2i(10, 20), Vec2i(20, 20)];
+    let sizes = vec![(8, 8), (8, 8)];
+    let colliders = vec![Collider::new(1, !TILES), Collider::new(1, TILES)];
+    let contacts = gather_contacts_tilemap_shaped(&positions, &sizes, &colliders, &maps, TILES);
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].a, 1);
+}
+
+#[test]
+fn tile_contacts_can_be_tracked() {
+    let maps = [map()];
+    let sizes = vec![(8, 8)];
+    let colliders = vec![Collider::trigger(1, u32::MAX)];
+    let mut tracker = ContactTracker::new();
+    let mut phases = |x: i32| {
+        let contacts = gather_contacts_tilemap_shaped(&[Vec2i(x, 20)], &sizes, &colliders, &maps, TILES);
+        tracker.update(&contacts).iter().map(|e| e.phase).collect::<Vec<_>>()
+    };
+    assert_eq!(phases(10), vec![ContactPhase::Began]);
+    assert_eq!(phases(12), vec![ContactPhase::Persisted]);
+    assert_eq!(phases(0), vec![ContactPhase::Ended]);
+    assert!(phases(0).is_empty());
+}
+
+#[test]
+fn walking_along_a_strip_of_tiles_is_one_contact() {
+    let maps = [common::map(&[
+        "........",
+        "..~~~~~.",
+        "########",
+    ])];
+    let sizes = vec![(16, 16)];
+    let colliders = vec![Collider::new(1, u32::MAX)];
+    let mut tracker = ContactTracker::new();
+    // Wading through the hazards, sunk a little into the floor, a few pixels further each tick
+    let mut phases = |x: i32| {
+        let contacts = gather_contacts_tilemap_shaped(&[Vec2i(x, 18)], &sizes, &colliders, &maps, TILES);
+        let mut events: Vec<_> = tracker.update(&contacts).iter().map(|e| (e.contact.b.tile.hazard, e.phase)).collect();
+        events.sort_by_key(|e| e.0);
+        events
+    };
+    assert_eq!(phases(32), vec![(false, ContactPhase::Began), (true, ContactPhase::Began)]);
+    for x in (35..=80).step_by(3) {
+        assert_eq!(phases(x), vec![(false, ContactPhase::Persisted), (true, ContactPhase::Persisted)], "at x = {}", x);
+    }
+    // Off the end of the hazards, but still on the floor
+    assert_eq!(phases(112), vec![(false, ContactPhase::Persisted), (true, ContactPhase::Ended)]);
+}
+
+#[test]
+fn merged_rects_cover_the_whole_block() {
+    let maps = [common::map(&[
+        "#.....",
+        "#.....",
+        "######",
+        "######",
+    ])];
+    let colliders = vec![Collider::new(1, u32::MAX)];
+    let mut rects: Vec<Rect> = gather_contacts_tilemap_shaped(&[Vec2i(40, 24)], &[(16, 16)], &colliders, &maps, TILES)
+        .iter()
+        .map(|c| c.b.rect)
+        .collect();
+    rects.sort_by_key(|r| (r.x, r.y));
+    assert_eq!(rects, vec![Rect::new(0, 32, 96, 32)]);
+    // The wall only reaches down to the floor, because the floor's rows are a different run
+    let contacts = gather_contacts_tilemap_shaped(&[Vec2i(8, 8)], &[(16, 16)], &colliders, &maps, TILES);
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].b.rect, Rect::new(0, 0, 16, 32));
+}
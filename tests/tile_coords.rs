@@ -0,0 +1,51 @@
+mod common;
+use common::tileset;
+use engine2d::tiles::*;
+use engine2d::types::*;
+
+#[test]
+fn from_world_rounds_down() {
+    let origin = Vec2i(0, 0);
+    let sz = (16, 16);
+    assert_eq!(TileCoord::from_world(Vec2i(0, 0), origin, sz), TileCoord(0, 0));
+    assert_eq!(TileCoord::from_world(Vec2i(15, 15), origin, sz), TileCoord(0, 0));
+    assert_eq!(TileCoord::from_world(Vec2i(16, 31), origin, sz), TileCoord(1, 1));
+    assert_eq!(TileCoord::from_world(Vec2i(-1, -1), origin, sz), TileCoord(-1, -1));
+    assert_eq!(TileCoord::from_world(Vec2i(-16, -17), origin, sz), TileCoord(-1, -2));
+}
+
+#[test]
+fn to_world_round_trips() {
+    let origin = Vec2i(-256, 40);
+    let sz = (16, 8);
+    for &c in &[TileCoord(0, 0), TileCoord(-3, 2), TileCoord(5, -7)] {
+        let Vec2i(x, y) = c.to_world(origin, sz);
+        assert_eq!(TileCoord::from_world(Vec2i(x, y), origin, sz), c);
+        assert_eq!(TileCoord::from_world(Vec2i(x + 15, y + 7), origin, sz), c);
+        assert_eq!(c.rect(origin, sz), Rect { x, y, w: 16, h: 8 });
+    }
+}
+
+#[test]
+fn nothing_just_outside_top_left() {
+    let tm = Tilemap::new(Vec2i(0, 0), (2, 2), &tileset(), vec![1, 1, 1, 1]);
+    assert!(tm.tile_at(Vec2i(-1, 0)).is_none());
+    assert!(tm.tile_at(Vec2i(0, -1)).is_none());
+    assert!(tm.tile_at(Vec2i(-15, -15)).is_none());
+    assert!(tm.tile_at(Vec2i(0, 0)).is_some());
+    assert_eq!(tm.tile_coord(Vec2i(-1, -1)), TileCoord(-1, -1));
+    assert!(!tm.in_bounds(TileCoord(-1, 0)));
+}
+
+#[test]
+fn map_at_negative_position() {
+    let tm = Tilemap::new(Vec2i(-32, -32), (2, 2), &tileset(), vec![0, 1, 1, 0]);
+    let (tile, rect) = tm.tile_at(Vec2i(-1, -32)).unwrap();
+    assert!(tile.solid);
+    assert_eq!(rect, Rect { x: -16, y: -32, w: 16, h: 16 });
+    let (tile, rect) = tm.tile_at(Vec2i(-17, -1)).unwrap();
+    assert!(tile.solid);
+    assert_eq!(rect, Rect { x: -32, y: -16, w: 16, h: 16 });
+    assert!(tm.tile_at(Vec2i(-33, -20)).is_none());
+    assert!(tm.tile_at(Vec2i(0, -20)).is_none());
+}
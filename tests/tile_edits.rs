@@ -0,0 +1,56 @@
+mod common;
+use common::map;
+use engine2d::tiles::*;
+
+fn ids(ns: &[usize]) -> Vec<TileID> {
+    ns.iter().map(|&n| TileID::new(n)).collect()
+}
+
+#[test]
+fn edits_off_the_map_are_errors_and_change_nothing() {
+    let mut m = map(&["....", "....", "...."]);
+    let solid = TileID::new(1);
+    assert_eq!(m.set_tile(4, 0, solid), Err(TileError::OutOfBounds(4, 0)));
+    // The far corner of the region is reported
+    assert_eq!(m.fill(TileRect::new(2, 1, 3, 1), solid), Err(TileError::OutOfBounds(4, 1)));
+    assert_eq!(m.fill(TileRect::new(0, 2, 1, 2), solid), Err(TileError::OutOfBounds(0, 3)));
+    assert_eq!(m.fill(TileRect::new(usize::MAX, 0, 2, 1), solid), Err(TileError::OutOfBounds(usize::MAX, 0)));
+    assert_eq!(m.fill(TileRect::new(0, 0, 1, 1), TileID::new(9)), Err(TileError::NoSuchTile(TileID::new(9))));
+    // Both the source and the destination have to fit
+    assert_eq!(m.copy_region(TileRect::new(3, 2, 2, 1), (0, 0)), Err(TileError::OutOfBounds(4, 2)));
+    assert_eq!(m.copy_region(TileRect::new(0, 0, 2, 2), (3, 0)), Err(TileError::OutOfBounds(4, 1)));
+    assert_eq!(m.ids(), &ids(&[0; 12])[..]);
+    assert!(!m.is_dirty());
+    // Regions right up to the edge are fine, and empty ones do nothing
+    m.fill(TileRect::new(0, 0, 4, 3), solid).unwrap();
+    m.fill(TileRect::new(4, 3, 0, 0), TileID::new(0)).unwrap();
+    assert_eq!(m.ids(), &ids(&[1; 12])[..]);
+}
+
+#[test]
+fn copies_can_overlap_themselves() {
+    let mut m = map(&["#~..", "...."]);
+    m.copy_region(TileRect::new(0, 0, 2, 1), (1, 0)).unwrap();
+    assert_eq!(m.ids(), &ids(&[1, 1, 3, 0, 0, 0, 0, 0])[..]);
+    m.copy_region(TileRect::new(1, 0, 3, 1), (1, 1)).unwrap();
+    assert_eq!(m.ids(), &ids(&[1, 1, 3, 0, 0, 1, 3, 0])[..]);
+}
+
+#[test]
+fn dirty_regions_merge_when_one_covers_another() {
+    let mut m = map(&["....", "....", "...."]);
+    // Setting a tile to what it already is changes nothing
+    m.set_tile(0, 0, TileID::new(0)).unwrap();
+    assert!(!m.is_dirty());
+    m.set_tile(1, 1, TileID::new(1)).unwrap();
+    m.set_tile(3, 2, TileID::new(1)).unwrap();
+    assert_eq!(m.take_dirty(), vec![TileRect::new(1, 1, 1, 1), TileRect::new(3, 2, 1, 1)]);
+    assert!(m.take_dirty().is_empty());
+    // A bigger edit swallows the smaller ones inside it, and later ones inside it aren't added
+    m.set_tile(1, 1, TileID::new(3)).unwrap();
+    m.set_tile(3, 2, TileID::new(3)).unwrap();
+    m.fill(TileRect::new(0, 0, 3, 2), TileID::new(2)).unwrap();
+    m.set_tile(2, 0, TileID::new(0)).unwrap();
+    m.copy_region(TileRect::new(0, 0, 2, 1), (1, 1)).unwrap();
+    assert_eq!(m.take_dirty(), vec![TileRect::new(3, 2, 1, 1), TileRect::new(0, 0, 3, 2)]);
+}
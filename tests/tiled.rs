@@ -0,0 +1,106 @@
+use engine2d::level::Spawn;
+use engine2d::tiled::*;
+use engine2d::tiles::*;
+use engine2d::types::*;
+
+// tests/tiled has the same 4x3 map saved both ways: level.tmx with its tileset embedded,
+// and level.tmj using the external tiles.tsx
+fn check_level(map: &TiledMap) {
+    assert_eq!(map.dims, (4, 3));
+    assert_eq!(map.tile_size, (16, 16));
+    assert_eq!(map.properties.get("music"), Some(&Property::Str("forest".to_string())));
+
+    assert_eq!(map.tilesets.len(), 1);
+    let ts = &map.tilesets[0];
+    assert_eq!(ts.tiles.len(), 8);
+    assert!(ts.tiles[1].solid);
+    assert!(ts.tiles[2].hazard && !ts.tiles[2].solid);
+    assert_eq!(ts.tiles[2].damage, 3);
+    assert_eq!(ts.tag(TileID::new(2), "sound"), Some(&TileTag::Str("sizzle".to_string())));
+    // 100ms and 250ms at 60 ticks a second
+    assert_eq!(ts.animations()[&3].frames, vec![(TileID::new(3), 6), (TileID::new(4), 15)]);
+
+    // Layers come back in file order, with the group's look folded into the layers inside it
+    let names: Vec<&str> = map.layers.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["ground", "trees"]);
+    let ground = &map.layers[0].tilemap;
+    let id = |n: usize| TileID::new(n);
+    let e = TileID::EMPTY;
+    assert_eq!(ground.ids(), &[e, e, e, e, e, e, id(3), e, id(1), id(1), id(2), id(1)][..]);
+    let trees = map.layer("trees").unwrap();
+    assert!(!trees.visible);
+    assert_eq!(trees.opacity, 0.25);
+    assert_eq!(trees.parallax, (0.5, 1.0));
+    assert_eq!(trees.tilemap.ids()[0], id(0));
+
+    // Objects are offset by their group, and tile objects are anchored at their bottom-left corner
+    assert_eq!(
+        map.objects,
+        vec![
+            Spawn { kind: "Player".to_string(), position: Vec2i(24, 8), size: (16, 16), name: "hero".to_string() },
+            Spawn { kind: "Coin".to_string(), position: Vec2i(40, 32), size: (16, 16), name: String::new() },
+        ]
+    );
+}
+
+#[test]
+fn tmx_maps_load() {
+    check_level(&TiledMap::load("tests/tiled/level.tmx").unwrap());
+}
+
+#[test]
+fn tmj_maps_with_external_tilesets_load() {
+    check_level(&TiledMap::load("tests/tiled/level.tmj").unwrap());
+}
+
+#[test]
+fn layers_stack_into_a_layered_map() {
+    let layered = TiledMap::load("tests/tiled/level.tmj").unwrap().into_layered().unwrap();
+    let colliding: Vec<&str> = layered.layers().iter().filter(|l| l.collides).map(|l| l.name.as_str()).collect();
+    assert_eq!(colliding, vec!["ground"]);
+    assert_eq!(layered.layers()[1].z, 1);
+    assert!(layered.solid_at(Vec2i(0, 40)).is_some());
+    assert!(layered.solid_at(Vec2i(0, 0)).is_none());
+}
+
+#[test]
+fn spawns_convert_to_entity_kinds() {
+    #[derive(Debug, PartialEq)]
+    enum Kind {
+        Player,
+        Coin,
+    }
+    impl std::str::FromStr for Kind {
+        type Err = ();
+        fn from_str(s: &str) -> Result<Self, ()> {
+            match s {
+                "Player" => Ok(Kind::Player),
+                "Coin" => Ok(Kind::Coin),
+                _ => Err(()),
+            }
+        }
+    }
+    let map = TiledMap::load("tests/tiled/level.tmx").unwrap();
+    let kinds: Vec<Kind> = map.spawns::<Kind>().unwrap().into_iter().map(|s| s.kind).collect();
+    assert_eq!(kinds, vec![Kind::Player, Kind::Coin]);
+    match map.spawns::<u32>() {
+        Err(TiledError::UnknownKind(kind)) => assert_eq!(kind, "Player"),
+        other => panic!("expected an unknown kind, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn unsupported_maps_are_errors() {
+    for (file, complaint) in &[
+        ("flipped.tmx", "flipped"),
+        ("mixed.tmx", "several tilesets"),
+        ("too_many_tiles.tmx", "only holds 8"),
+        ("wrong_columns.tmx", "8 tiles across"),
+    ] {
+        match TiledMap::load(format!("tests/tiled/{}", file)) {
+            Err(TiledError::Unsupported(what)) => assert!(what.contains(complaint), "{}: {}", file, what),
+            Err(e) => panic!("{}: expected it to be unsupported, got {}", file, e),
+            Ok(_) => panic!("{} loaded", file),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="tiles" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+ <image source="tiles.png" width="64" height="32"/>
+ <tile id="1">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="2">
+  <properties>
+   <property name="hazard" type="bool" value="true"/>
+   <property name="damage" type="int" value="3"/>
+   <property name="sound" value="sizzle"/>
+  </properties>
+ </tile>
+ <tile id="3">
+  <animation>
+   <frame tileid="3" duration="100"/>
+   <frame tileid="4" duration="250"/>
+  </animation>
+ </tile>
+</tileset>